@@ -0,0 +1,275 @@
+//! `#[derive(BleProfile)]` for [blec](https://docs.rs/blec), use it through the `derive` feature of blec.
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::{format_ident, quote};
+use std::collections::HashMap;
+use syn::ext::IdentExt;
+use syn::{parse_macro_input, Attribute, Data, DeriveInput, Error, Fields, Ident, LitStr, Type};
+
+/// Generates a typed client for a device with a fixed service, see `blec::profile` for an example.
+///
+/// The struct needs `#[ble(service = "<uuid>")]`, every field
+/// `#[ble(charac = "<uuid>", read, write, notify, codec = Type)]` where the properties and codec are optional.
+/// Generic structs are not supported, raw field names like `r#type` generate `TYPE` and `read_type`.
+#[proc_macro_derive(BleProfile, attributes(ble))]
+pub fn derive_ble_profile(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(input)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+struct Charac {
+    field: Ident,
+    /// field name without `r#`, used for the constant and method names
+    name: Ident,
+    ty: Type,
+    uuid: u128,
+    read: bool,
+    write: bool,
+    notify: bool,
+    codec: Option<Type>,
+}
+
+fn parse_uuid(lit: &LitStr) -> syn::Result<u128> {
+    uuid::Uuid::parse_str(&lit.value())
+        .map(|uuid| uuid.as_u128())
+        .map_err(|e| Error::new(lit.span(), format!("invalid uuid: {e}")))
+}
+
+fn ble_attr(attrs: &[Attribute]) -> Option<&Attribute> {
+    attrs.iter().find(|attr| attr.path().is_ident("ble"))
+}
+
+fn parse_service(input: &DeriveInput) -> syn::Result<u128> {
+    let missing = || Error::new(input.ident.span(), "missing #[ble(service = \"<uuid>\")]");
+    let attr = ble_attr(&input.attrs).ok_or_else(missing)?;
+    let mut service = None;
+    attr.parse_nested_meta(|meta| {
+        if meta.path.is_ident("service") {
+            service = Some(parse_uuid(&meta.value()?.parse()?)?);
+            Ok(())
+        } else {
+            Err(meta.error("expected `service`"))
+        }
+    })?;
+    service.ok_or_else(missing)
+}
+
+fn parse_charac(field: &syn::Field) -> syn::Result<Charac> {
+    let ident = field.ident.clone().expect("named field");
+    let missing = || Error::new(ident.span(), "missing #[ble(charac = \"<uuid>\", ...)]");
+    let attr = ble_attr(&field.attrs).ok_or_else(missing)?;
+    let mut charac = Charac {
+        field: ident.clone(),
+        name: ident.unraw(),
+        ty: field.ty.clone(),
+        uuid: 0,
+        read: false,
+        write: false,
+        notify: false,
+        codec: None,
+    };
+    let mut uuid = None;
+    attr.parse_nested_meta(|meta| {
+        if meta.path.is_ident("charac") {
+            uuid = Some(parse_uuid(&meta.value()?.parse()?)?);
+        } else if meta.path.is_ident("read") {
+            charac.read = true;
+        } else if meta.path.is_ident("write") {
+            charac.write = true;
+        } else if meta.path.is_ident("notify") {
+            charac.notify = true;
+        } else if meta.path.is_ident("codec") {
+            charac.codec = Some(meta.value()?.parse()?);
+        } else {
+            return Err(meta.error("expected `charac`, `read`, `write`, `notify` or `codec`"));
+        }
+        Ok(())
+    })?;
+    charac.uuid = uuid.ok_or_else(missing)?;
+    Ok(charac)
+}
+
+fn expand(input: DeriveInput) -> syn::Result<TokenStream2> {
+    let fields = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => &fields.named,
+            _ => return Err(Error::new_spanned(&input, "BleProfile needs named fields")),
+        },
+        _ => {
+            return Err(Error::new_spanned(
+                &input,
+                "BleProfile can only be derived for structs",
+            ))
+        }
+    };
+    if input.generics.lt_token.is_some() || input.generics.where_clause.is_some() {
+        return Err(Error::new_spanned(
+            &input.generics,
+            "BleProfile can't be derived for generic structs",
+        ));
+    }
+    let service = parse_service(&input)?;
+    let characs = fields
+        .iter()
+        .map(parse_charac)
+        .collect::<syn::Result<Vec<_>>>()?;
+
+    let name = &input.ident;
+    let vis = &input.vis;
+    let client = format_ident!("{name}Client");
+    let consts = constants(&characs)?;
+    if characs.iter().all(|c| c.read) {
+        if let Some(all) = characs.iter().find(|c| c.name == "all") {
+            return Err(Error::new(
+                all.field.span(),
+                "`read_all` of this field collides with the method reading all fields",
+            ));
+        }
+    }
+    let uuids = characs.iter().map(|c| c.uuid);
+    let requirements = characs.iter().zip(&consts).map(|(c, constant)| {
+        let (read, write, notify) = (c.read, c.write, c.notify);
+        quote! {
+            ::blec::profile::Requirement {
+                uuid: #name::#constant,
+                read: #read,
+                write: #write,
+                notify: #notify,
+            }
+        }
+    });
+    let methods = characs
+        .iter()
+        .zip(&consts)
+        .map(|(c, constant)| methods(name, c, constant));
+    let read_all = characs.iter().all(|c| c.read).then(|| {
+        let fields = characs.iter().map(|c| &c.field);
+        let reads = characs.iter().map(|c| format_ident!("read_{}", c.name));
+        quote! {
+            /// Reads all characteristics
+            pub async fn read_all(&self) -> ::core::result::Result<#name, ::blec::BleError> {
+                ::core::result::Result::Ok(#name {
+                    #(#fields: self.#reads().await?,)*
+                })
+            }
+        }
+    });
+    let client_doc = format!("Client for [{name}] generated by `BleProfile`");
+
+    Ok(quote! {
+        impl #name {
+            pub const SERVICE: ::blec::profile::Uuid = ::blec::profile::Uuid::from_u128(#service);
+            #(pub const #consts: ::blec::profile::Uuid = ::blec::profile::Uuid::from_u128(#uuids);)*
+        }
+
+        #[doc = #client_doc]
+        #[derive(Debug)]
+        #vis struct #client {
+            _connected: (),
+        }
+
+        impl #client {
+            /// Connects to the device and checks that it supports all characteristics with the required properties
+            pub async fn connect(
+                target: impl ::core::convert::Into<::blec::ConnectTarget>,
+                on_disconnect: ::core::option::Option<
+                    impl FnOnce(::blec::BleDevice, ::blec::DisconnectReason) + Send + 'static,
+                >,
+            ) -> ::core::result::Result<Self, ::blec::BleError> {
+                ::blec::profile::connect_profile(
+                    target,
+                    #name::SERVICE,
+                    &[#(#requirements),*],
+                    on_disconnect,
+                )
+                .await?;
+                ::core::result::Result::Ok(Self { _connected: () })
+            }
+
+            pub async fn disconnect(self) -> ::core::result::Result<(), ::blec::BleError> {
+                ::blec::disconnect().await
+            }
+
+            #(#methods)*
+            #read_all
+        }
+    })
+}
+
+/// Uuid constants named after the fields in upper case, they must not collide with each other or `SERVICE`
+fn constants(characs: &[Charac]) -> syn::Result<Vec<Ident>> {
+    let mut taken = HashMap::from([("SERVICE".to_string(), None)]);
+    let mut consts = vec![];
+    for c in characs {
+        let constant = c.name.to_string().to_uppercase();
+        if let Some(other) = taken.get(&constant) {
+            let with = match other {
+                Some(other) => format!("the one of field `{other}`"),
+                None => "the service uuid".to_string(),
+            };
+            return Err(Error::new(
+                c.field.span(),
+                format!("the constant `{constant}` of this field collides with {with}"),
+            ));
+        }
+        taken.insert(constant.clone(), Some(c.name.clone()));
+        consts.push(Ident::new(&constant, c.field.span()));
+    }
+    Ok(consts)
+}
+
+/// read, write and subscribe methods of the declared properties
+fn methods(name: &Ident, c: &Charac, constant: &Ident) -> TokenStream2 {
+    let ty = &c.ty;
+    let mut methods = TokenStream2::new();
+    if c.read {
+        let method = format_ident!("read_{}", c.name);
+        let body = match &c.codec {
+            Some(codec) => quote! {
+                let value: #codec = ::blec::read(#name::#constant).await?;
+                ::core::result::Result::Ok(::core::convert::Into::into(value))
+            },
+            None => quote!(::blec::read(#name::#constant).await),
+        };
+        methods.extend(quote! {
+            pub async fn #method(&self) -> ::core::result::Result<#ty, ::blec::BleError> {
+                #body
+            }
+        });
+    }
+    if c.write {
+        let method = format_ident!("write_{}", c.name);
+        let value = match &c.codec {
+            Some(codec) => quote!(<#codec as ::core::convert::From<#ty>>::from(value)),
+            None => quote!(value),
+        };
+        methods.extend(quote! {
+            pub async fn #method(&self, value: #ty) -> ::core::result::Result<(), ::blec::BleError> {
+                ::blec::write(#name::#constant, &#value).await
+            }
+        });
+    }
+    if c.notify {
+        let method = format_ident!("subscribe_{}", c.name);
+        let callback = match &c.codec {
+            Some(codec) => quote! {
+                move |value: ::core::result::Result<#codec, ::blec::BleError>| {
+                    callback(value.map(::core::convert::Into::into))
+                }
+            },
+            None => quote!(callback),
+        };
+        methods.extend(quote! {
+            pub async fn #method(
+                &self,
+                callback: impl Fn(::core::result::Result<#ty, ::blec::BleError>) + Send + Sync + 'static,
+            ) -> ::core::result::Result<(), ::blec::BleError> {
+                ::blec::subscribe_typed(#name::#constant, #callback).await
+            }
+        });
+    }
+    methods
+}
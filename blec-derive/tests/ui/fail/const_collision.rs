@@ -0,0 +1,20 @@
+use blec::BleProfile;
+
+#[derive(BleProfile)]
+#[ble(service = "0000180f-0000-1000-8000-00805f9b34fb")]
+struct Device {
+    #[ble(charac = "00002a19-0000-1000-8000-00805f9b34fb", read)]
+    service: u8,
+}
+
+#[derive(BleProfile)]
+#[ble(service = "0000180f-0000-1000-8000-00805f9b34fb")]
+#[allow(non_snake_case)]
+struct Battery {
+    #[ble(charac = "00002a19-0000-1000-8000-00805f9b34fb", read)]
+    level: u8,
+    #[ble(charac = "00002a1a-0000-1000-8000-00805f9b34fb", read)]
+    Level: u8,
+}
+
+fn main() {}
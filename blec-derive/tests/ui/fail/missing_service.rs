@@ -0,0 +1,9 @@
+use blec::BleProfile;
+
+#[derive(BleProfile)]
+struct Battery {
+    #[ble(charac = "00002a19-0000-1000-8000-00805f9b34fb", read)]
+    level: u8,
+}
+
+fn main() {}
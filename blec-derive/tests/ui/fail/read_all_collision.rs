@@ -0,0 +1,10 @@
+use blec::BleProfile;
+
+#[derive(BleProfile)]
+#[ble(service = "0000180f-0000-1000-8000-00805f9b34fb")]
+struct Battery {
+    #[ble(charac = "00002a19-0000-1000-8000-00805f9b34fb", read)]
+    all: u8,
+}
+
+fn main() {}
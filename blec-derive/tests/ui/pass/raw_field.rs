@@ -0,0 +1,20 @@
+use blec::BleProfile;
+
+#[derive(BleProfile)]
+#[ble(service = "0000180f-0000-1000-8000-00805f9b34fb")]
+struct Sensor {
+    #[ble(charac = "00002a19-0000-1000-8000-00805f9b34fb", read, write)]
+    r#type: u8,
+}
+
+#[allow(dead_code)]
+async fn example(sensor: SensorClient) -> Result<(), blec::BleError> {
+    sensor.write_type(1).await?;
+    let Sensor { r#type } = sensor.read_all().await?;
+    assert_eq!(r#type, sensor.read_type().await?);
+    Ok(())
+}
+
+fn main() {
+    assert_eq!(Sensor::TYPE.as_u128() >> 96, 0x2a19);
+}
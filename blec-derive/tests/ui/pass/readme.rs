@@ -0,0 +1,26 @@
+// the example of the README
+use blec::{BigEndian, BleAddress, BleError, BleProfile};
+
+#[derive(BleProfile)]
+#[ble(service = "0000180f-0000-1000-8000-00805f9b34fb")]
+struct Battery {
+    #[ble(charac = "00002a19-0000-1000-8000-00805f9b34fb", read, notify)]
+    level: u8,
+    #[ble(charac = "12345678-1234-5678-1234-56789abcdef0", read, write, codec = BigEndian<u16>)]
+    threshold: u16,
+}
+
+#[allow(dead_code)]
+async fn example(address: BleAddress) -> Result<(), BleError> {
+    let battery = BatteryClient::connect(address, None::<fn(_, _)>).await?;
+    battery.subscribe_level(|level| println!("{level:?}")).await?;
+    battery.write_threshold(1000).await?;
+    let Battery { level, threshold } = battery.read_all().await?;
+    println!("{level} {threshold}");
+    battery.disconnect().await
+}
+
+fn main() {
+    assert_eq!(Battery::SERVICE.as_u128() >> 96, 0x180f);
+    assert_eq!(Battery::LEVEL.as_u128() >> 96, 0x2a19);
+}
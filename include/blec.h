@@ -0,0 +1,211 @@
+#ifndef BLEC_H
+#define BLEC_H
+
+/* Generated with cbindgen from src/ffi.rs, do not edit. */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+/**
+ * Result of all calls, `BLEC_RESULT_OK` on success
+ */
+typedef enum BlecResult {
+  BLEC_RESULT_OK = 0,
+  /**
+   * a null pointer, invalid UUID or address was passed
+   */
+  BLEC_RESULT_INVALID_ARGUMENT,
+  BLEC_RESULT_NOT_INITIALIZED,
+  BLEC_RESULT_NO_DEVICE_CONNECTED,
+  BLEC_RESULT_ALREADY_CONNECTED,
+  BLEC_RESULT_DEVICE_NOT_FOUND,
+  BLEC_RESULT_SERVICE_NOT_FOUND,
+  BLEC_RESULT_CHARAC_NOT_AVAILABLE,
+  BLEC_RESULT_ADAPTER_OFF,
+  BLEC_RESULT_NO_ADAPTERS,
+  /**
+   * the call was made from inside an async context
+   */
+  BLEC_RESULT_BLOCKING_IN_ASYNC_CONTEXT,
+  /**
+   * blec panicked, see [blec_last_error_message]
+   */
+  BLEC_RESULT_PANIC,
+  /**
+   * any other error, see [blec_last_error_message]
+   */
+  BLEC_RESULT_OTHER,
+} BlecResult;
+
+typedef enum BlecDisconnectReason {
+  BLEC_DISCONNECT_REASON_USER_INITIATED,
+  BLEC_DISCONNECT_REASON_REMOTE,
+  BLEC_DISCONNECT_REASON_LINK_LOSS,
+  BLEC_DISCONNECT_REASON_ADAPTER_OFF,
+} BlecDisconnectReason;
+
+/**
+ * Opaque device, accessed with the `blec_device_*` functions
+ */
+typedef struct BlecDevice BlecDevice;
+
+/**
+ * Opaque list of devices returned by [blec_discover], free it with [blec_device_list_free]
+ */
+typedef struct BlecDeviceList BlecDeviceList;
+
+/**
+ * Called once when the connection ends, `device` is only valid during the call. Can be null.
+ */
+typedef void (*BlecDisconnectCallback)(void *user_data,
+                                       const struct BlecDevice *device,
+                                       enum BlecDisconnectReason reason);
+
+/**
+ * Called with the data of every notification, `data` is only valid during the call. Must not be null.
+ */
+typedef void (*BlecNotifyCallback)(void *user_data, const uint8_t *data, size_t len);
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+/**
+ * Initializes blec, has to be called before anything else. Calling it again does nothing.
+ */
+enum BlecResult blec_init(void);
+
+/**
+ * Disconnects and stops blec, [blec_init] can be called again afterwards
+ */
+enum BlecResult blec_deinit(void);
+
+/**
+ * Returns the message of the last error on this thread or null.
+ * The string is valid until the next failing blec call on this thread.
+ */
+const char *blec_last_error_message(void);
+
+/**
+ * Scans for `timeout_ms` milliseconds and stores the discovered devices in `out`.
+ *
+ * # Safety
+ * `out` must be a valid pointer, the list stored in it must be freed with [blec_device_list_free].
+ */
+enum BlecResult blec_discover(uint64_t timeout_ms, struct BlecDeviceList **out);
+
+/**
+ * # Safety
+ * `list` must be null or returned by [blec_discover]
+ */
+size_t blec_device_list_len(const struct BlecDeviceList *list);
+
+/**
+ * Returns the device at `index` or null, it is valid until the list is freed.
+ *
+ * # Safety
+ * `list` must be null or returned by [blec_discover]
+ */
+const struct BlecDevice *blec_device_list_get(const struct BlecDeviceList *list, size_t index);
+
+/**
+ * # Safety
+ * `list` must be null or returned by [blec_discover] and not be used afterwards
+ */
+void blec_device_list_free(struct BlecDeviceList *list);
+
+/**
+ * Returns the local name of the device, valid as long as the device
+ *
+ * # Safety
+ * `device` must be a valid device pointer
+ */
+const char *blec_device_name(const struct BlecDevice *device);
+
+/**
+ * Writes the 6 address bytes, most significant first, to `out`
+ *
+ * # Safety
+ * `device` must be a valid device pointer and `out` must point to 6 writable bytes
+ */
+enum BlecResult blec_device_address(const struct BlecDevice *device, uint8_t *out);
+
+/**
+ * Returns the rssi of the device or `INT16_MIN` if unknown
+ *
+ * # Safety
+ * `device` must be a valid device pointer
+ */
+int16_t blec_device_rssi(const struct BlecDevice *device);
+
+/**
+ * Connects to the device with the given address (`AA:BB:CC:DD:EE:FF`).
+ * `on_disconnect` is optional and called once when the connection ends.
+ *
+ * # Safety
+ * All strings must be valid nul terminated strings, `characs` must point to `characs_len` strings.
+ * `user_data` is passed to the callback from another thread.
+ */
+enum BlecResult blec_connect(const char *address,
+                             const char *service,
+                             const char *const *characs,
+                             size_t characs_len,
+                             BlecDisconnectCallback on_disconnect,
+                             void *user_data);
+
+/**
+ * Connects to a device returned by [blec_discover] using its platform-stable id.
+ *
+ * # Safety
+ * Same as [blec_connect], `device` must be a valid device pointer.
+ */
+enum BlecResult blec_connect_device(const struct BlecDevice *device,
+                                    const char *service,
+                                    const char *const *characs,
+                                    size_t characs_len,
+                                    BlecDisconnectCallback on_disconnect,
+                                    void *user_data);
+
+enum BlecResult blec_disconnect(void);
+
+/**
+ * Writes `len` bytes to the characteristic.
+ *
+ * # Safety
+ * `charac` must be a valid nul terminated string and `data` must point to `len` bytes
+ */
+enum BlecResult blec_send(const char *charac, const uint8_t *data, size_t len);
+
+/**
+ * Reads the characteristic, the data stored in `out_data`/`out_len` must be freed with [blec_buffer_free].
+ *
+ * # Safety
+ * `charac` must be a valid nul terminated string, `out_data` and `out_len` must be valid pointers
+ */
+enum BlecResult blec_recv(const char *charac,
+                          uint8_t **out_data,
+                          size_t *out_len);
+
+/**
+ * # Safety
+ * `data` and `len` must be returned by [blec_recv] and `data` must not be used afterwards
+ */
+void blec_buffer_free(uint8_t *data, size_t len);
+
+/**
+ * Calls `callback` with every notification of the characteristic, `callback` must not be null.
+ *
+ * # Safety
+ * `charac` must be a valid nul terminated string.
+ * `user_data` is passed to the callback from another thread.
+ */
+enum BlecResult blec_subscribe(const char *charac, BlecNotifyCallback callback, void *user_data);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* BLEC_H */
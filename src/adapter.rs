@@ -0,0 +1,160 @@
+use crate::BleError;
+use btleplug::api::{Central, CentralState, Manager as _};
+use btleplug::platform::{Adapter, Manager};
+use once_cell::sync::Lazy;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use std::fmt::{self, Debug, Formatter};
+use std::sync::Arc;
+use tokio::sync::watch;
+
+/// Current state of the adapter, kept outside of the handler so it can be updated while an operation is running
+static ADAPTER_STATE: Lazy<watch::Sender<AdapterState>> =
+    Lazy::new(|| watch::channel(AdapterState::Unknown).0);
+
+/// Information about a bluetooth adapter available on the system
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct AdapterInfo {
+    /// position in the list of adapters reported by the platform
+    pub index: usize,
+    /// first word of [info](AdapterInfo::info), e.g. `hci0` on Linux
+    pub name: String,
+    /// platform specific description of the adapter
+    pub info: String,
+}
+
+impl AdapterInfo {
+    fn new(index: usize, info: String) -> Self {
+        let name = info.split_whitespace().next().unwrap_or_default().into();
+        Self { index, name, info }
+    }
+}
+
+/// Power and availability of the adapter in use
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "uniffi", derive(uniffi::Enum))]
+#[cfg_attr(feature = "python", pyo3::pyclass(eq, eq_int, module = "blec"))]
+pub enum AdapterState {
+    /// not reported by the platform (yet)
+    Unknown,
+    PoweredOn,
+    PoweredOff,
+    /// the adapter is gone or its state can't be read
+    Unavailable,
+    /// missing bluetooth permissions
+    Unauthorized,
+}
+
+impl AdapterState {
+    /// Operations are only attempted while this is true
+    pub fn is_available(&self) -> bool {
+        matches!(self, Self::Unknown | Self::PoweredOn)
+    }
+}
+
+impl From<CentralState> for AdapterState {
+    fn from(state: CentralState) -> Self {
+        match state {
+            CentralState::Unknown => Self::Unknown,
+            CentralState::PoweredOn => Self::PoweredOn,
+            CentralState::PoweredOff => Self::PoweredOff,
+        }
+    }
+}
+
+/// Selects the adapter used by blec
+#[derive(Clone, Default)]
+pub enum AdapterSelector {
+    /// first adapter reported by the platform
+    #[default]
+    First,
+    /// adapter at the given position in [list_adapters](crate::list_adapters)
+    Index(usize),
+    /// adapter with the given [name](AdapterInfo::name)
+    Name(String),
+    /// first adapter the predicate returns true for
+    Predicate(Arc<dyn Fn(&AdapterInfo) -> bool + Send + Sync>),
+}
+
+impl AdapterSelector {
+    fn matches(&self, info: &AdapterInfo) -> bool {
+        match self {
+            Self::First => true,
+            Self::Index(index) => info.index == *index,
+            Self::Name(name) => &info.name == name,
+            Self::Predicate(predicate) => predicate(info),
+        }
+    }
+}
+
+impl Debug for AdapterSelector {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            Self::First => write!(f, "First"),
+            Self::Index(index) => f.debug_tuple("Index").field(index).finish(),
+            Self::Name(name) => f.debug_tuple("Name").field(name).finish(),
+            Self::Predicate(_) => write!(f, "Predicate"),
+        }
+    }
+}
+
+pub(crate) async fn list_adapters(
+    manager: &Manager,
+) -> Result<Vec<(Adapter, AdapterInfo)>, BleError> {
+    let mut adapters = vec![];
+    for (i, adapter) in manager.adapters().await?.into_iter().enumerate() {
+        let info = AdapterInfo::new(i, adapter.adapter_info().await?);
+        adapters.push((adapter, info));
+    }
+    Ok(adapters)
+}
+
+pub(crate) async fn select_adapter(
+    manager: &Manager,
+    selector: &AdapterSelector,
+) -> Result<(Adapter, AdapterInfo), BleError> {
+    let adapters = list_adapters(manager).await?;
+    if adapters.is_empty() {
+        return Err(BleError::NoAdapters);
+    }
+    adapters
+        .into_iter()
+        .find(|(_, info)| selector.matches(info))
+        .ok_or(BleError::AdapterNotFound(format!("{selector:?}")))
+}
+
+pub(crate) async fn query_state(adapter: &Adapter) -> AdapterState {
+    match adapter.adapter_state().await {
+        Ok(state) => state.into(),
+        Err(btleplug::Error::NotSupported(_)) => AdapterState::Unknown,
+        Err(btleplug::Error::PermissionDenied) => AdapterState::Unauthorized,
+        Err(_) => AdapterState::Unavailable,
+    }
+}
+
+pub(crate) fn current_state() -> AdapterState {
+    *ADAPTER_STATE.borrow()
+}
+
+/// Returns true if the state changed
+pub(crate) fn set_state(state: AdapterState) -> bool {
+    ADAPTER_STATE.send_if_modified(|current| {
+        let changed = *current != state;
+        *current = state;
+        changed
+    })
+}
+
+pub(crate) fn state_receiver() -> watch::Receiver<AdapterState> {
+    ADAPTER_STATE.subscribe()
+}
+
+pub(crate) fn ensure_available() -> Result<(), BleError> {
+    if current_state().is_available() {
+        Ok(())
+    } else {
+        Err(BleError::AdapterOff)
+    }
+}
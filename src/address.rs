@@ -0,0 +1,317 @@
+use crate::ParseBleAddressError;
+use btleplug::api::{AddressType, BDAddr};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use std::fmt::{self, Display, Formatter};
+use std::str::FromStr;
+
+#[derive(Debug, Clone, Copy, Ord, Eq, PartialOrd, PartialEq, Hash, Default)]
+pub struct BleAddress {
+    pub address: [u8; 6],
+}
+
+/// Letter case used when formatting an address
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum AddressCase {
+    #[default]
+    Upper,
+    Lower,
+}
+
+/// Type of the address as reported by the platform
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum BleAddressType {
+    Public,
+    Random,
+}
+
+/// Sub type of a random address, encoded in the two most significant bits
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum RandomAddressKind {
+    /// `0b11`, fixed for the lifetime of the device or until power cycle
+    Static,
+    /// `0b01`, changes periodically and can be resolved with the identity resolving key of a bonded device
+    ResolvablePrivate,
+    /// `0b00`, changes periodically and cannot be resolved
+    NonResolvablePrivate,
+}
+
+impl PartialEq<BDAddr> for BleAddress {
+    fn eq(&self, other: &BDAddr) -> bool {
+        self.address.eq(&other.into_inner())
+    }
+}
+impl From<BDAddr> for BleAddress {
+    fn from(addr: BDAddr) -> Self {
+        Self {
+            address: addr.into_inner(),
+        }
+    }
+}
+impl From<AddressType> for BleAddressType {
+    fn from(address_type: AddressType) -> Self {
+        match address_type {
+            AddressType::Public => Self::Public,
+            AddressType::Random => Self::Random,
+        }
+    }
+}
+impl Display for BleAddress {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        self.write(f, Some(':'), AddressCase::Upper)
+    }
+}
+impl FromStr for BleAddress {
+    type Err = ParseBleAddressError;
+
+    /// Parses `AA:BB:CC:DD:EE:FF`, `AA-BB-CC-DD-EE-FF` and `AABBCCDDEEFF` in any letter case
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        parse(s, &[':', '-'], true)
+    }
+}
+impl TryFrom<&str> for BleAddress {
+    type Error = ParseBleAddressError;
+
+    fn try_from(s: &str) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+impl BleAddress {
+    // Parses a Bluetooth address with colons `:` as delimiters.
+    pub fn from_str_delim(addr_str: &str) -> Result<Self, ParseBleAddressError> {
+        parse(addr_str, &[':'], false)
+    }
+
+    /// Formats the address with an optional delimiter between the bytes
+    pub fn format(&self, delimiter: Option<char>, case: AddressCase) -> String {
+        let mut s = String::with_capacity(17);
+        // writing to a String can't fail
+        let _ = self.write(&mut s, delimiter, case);
+        s
+    }
+
+    fn write(
+        &self,
+        w: &mut impl fmt::Write,
+        delimiter: Option<char>,
+        case: AddressCase,
+    ) -> fmt::Result {
+        for (i, b) in self.address.iter().enumerate() {
+            if i > 0 {
+                if let Some(d) = delimiter {
+                    w.write_char(d)?;
+                }
+            }
+            match case {
+                AddressCase::Upper => write!(w, "{b:02X}")?,
+                AddressCase::Lower => write!(w, "{b:02x}")?,
+            }
+        }
+        Ok(())
+    }
+
+    /// Kind of the address assuming it is a random address.
+    /// Returns `None` for the reserved pattern `0b10`.
+    pub fn random_kind(&self) -> Option<RandomAddressKind> {
+        match self.address[0] >> 6 {
+            0b11 => Some(RandomAddressKind::Static),
+            0b01 => Some(RandomAddressKind::ResolvablePrivate),
+            0b00 => Some(RandomAddressKind::NonResolvablePrivate),
+            _ => None,
+        }
+    }
+
+    pub fn is_static_random(&self) -> bool {
+        self.random_kind() == Some(RandomAddressKind::Static)
+    }
+
+    pub fn is_resolvable_private(&self) -> bool {
+        self.random_kind() == Some(RandomAddressKind::ResolvablePrivate)
+    }
+
+    pub fn is_non_resolvable_private(&self) -> bool {
+        self.random_kind() == Some(RandomAddressKind::NonResolvablePrivate)
+    }
+}
+
+/// Parses 6 hex encoded bytes, separated by one of [delimiters] or, if [allow_plain] is set, not separated at all.
+/// The same delimiter has to be used throughout the address.
+fn parse(
+    s: &str,
+    delimiters: &[char],
+    allow_plain: bool,
+) -> Result<BleAddress, ParseBleAddressError> {
+    let chars: Vec<char> = s.chars().collect();
+    let delimiter = match chars.len() {
+        17 => Some(chars[2]),
+        12 if allow_plain => None,
+        len => return Err(ParseBleAddressError::InvalidLength(len)),
+    };
+    let step = if delimiter.is_some() { 3 } else { 2 };
+    let mut address = [0; 6];
+    for (i, byte) in address.iter_mut().enumerate() {
+        let start = i * step;
+        if let (Some(d), true) = (delimiter, i > 0) {
+            let c = chars[start - 1];
+            if c != d || !delimiters.contains(&c) {
+                return Err(ParseBleAddressError::InvalidDelimiter {
+                    delimiter: c,
+                    position: start - 1,
+                });
+            }
+        }
+        *byte = hex_digit(chars[start], start)? << 4 | hex_digit(chars[start + 1], start + 1)?;
+    }
+    Ok(BleAddress { address })
+}
+
+fn hex_digit(c: char, position: usize) -> Result<u8, ParseBleAddressError> {
+    c.to_digit(16)
+        .map(|d| d as u8)
+        .ok_or(ParseBleAddressError::InvalidCharacter {
+            character: c,
+            position,
+        })
+}
+
+/// serialized in the canonical `AA:BB:CC:DD:EE:FF` form
+#[cfg(feature = "serde")]
+impl Serialize for BleAddress {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+/// accepts all forms supported by [FromStr]
+#[cfg(feature = "serde")]
+impl<'de> Deserialize<'de> for BleAddress {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        s.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ADDRESS: BleAddress = BleAddress {
+        address: [0xAA, 0xBB, 0xCC, 0xDD, 0xEE, 0x0F],
+    };
+
+    fn address(first: u8) -> BleAddress {
+        BleAddress {
+            address: [first, 0, 0, 0, 0, 0],
+        }
+    }
+
+    #[test]
+    fn parses_both_delimiters_and_plain() {
+        for s in ["AA:BB:CC:DD:EE:0F", "aa-bb-cc-dd-ee-0f", "AaBbCcDdEe0f"] {
+            assert_eq!(s.parse::<BleAddress>(), Ok(ADDRESS), "{s}");
+        }
+        assert_eq!(BleAddress::from_str_delim("AA:BB:CC:DD:EE:0F"), Ok(ADDRESS));
+    }
+
+    #[test]
+    fn from_str_delim_only_accepts_colons() {
+        assert_eq!(
+            BleAddress::from_str_delim("AA-BB-CC-DD-EE-0F"),
+            Err(ParseBleAddressError::InvalidDelimiter {
+                delimiter: '-',
+                position: 2
+            })
+        );
+        assert_eq!(
+            BleAddress::from_str_delim("AABBCCDDEE0F"),
+            Err(ParseBleAddressError::InvalidLength(12))
+        );
+    }
+
+    #[test]
+    fn rejects_mixed_or_unknown_delimiters() {
+        assert_eq!(
+            "AA:BB-CC:DD:EE:0F".parse::<BleAddress>(),
+            Err(ParseBleAddressError::InvalidDelimiter {
+                delimiter: '-',
+                position: 5
+            })
+        );
+        assert_eq!(
+            "AA.BB.CC.DD.EE.0F".parse::<BleAddress>(),
+            Err(ParseBleAddressError::InvalidDelimiter {
+                delimiter: '.',
+                position: 2
+            })
+        );
+    }
+
+    #[test]
+    fn rejects_wrong_lengths() {
+        for (s, len) in [("", 0), ("AA:BB:CC:DD:EE", 14), ("AA:BB:CC:DD:EE:0F:", 18)] {
+            assert_eq!(
+                s.parse::<BleAddress>(),
+                Err(ParseBleAddressError::InvalidLength(len)),
+                "{s}"
+            );
+        }
+        // counted in characters, not bytes
+        assert_eq!(
+            "ÄA:BB:CC:DD:EE:0F".parse::<BleAddress>(),
+            Err(ParseBleAddressError::InvalidCharacter {
+                character: 'Ä',
+                position: 0
+            })
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_hex() {
+        assert_eq!(
+            "AA:BB:CC:DD:EE:0G".parse::<BleAddress>(),
+            Err(ParseBleAddressError::InvalidCharacter {
+                character: 'G',
+                position: 16
+            })
+        );
+        assert_eq!(
+            "AABBCC+DEE0F".parse::<BleAddress>(),
+            Err(ParseBleAddressError::InvalidCharacter {
+                character: '+',
+                position: 6
+            })
+        );
+    }
+
+    #[test]
+    fn formats() {
+        assert_eq!(ADDRESS.to_string(), "AA:BB:CC:DD:EE:0F");
+        assert_eq!(
+            ADDRESS.format(Some('-'), AddressCase::Lower),
+            "aa-bb-cc-dd-ee-0f"
+        );
+        assert_eq!(ADDRESS.format(None, AddressCase::Upper), "AABBCCDDEE0F");
+    }
+
+    #[test]
+    fn random_kinds() {
+        assert_eq!(address(0xC0).random_kind(), Some(RandomAddressKind::Static));
+        assert!(address(0xFF).is_static_random());
+        assert_eq!(
+            address(0x40).random_kind(),
+            Some(RandomAddressKind::ResolvablePrivate)
+        );
+        assert!(address(0x7F).is_resolvable_private());
+        assert_eq!(
+            address(0x00).random_kind(),
+            Some(RandomAddressKind::NonResolvablePrivate)
+        );
+        assert!(address(0x3F).is_non_resolvable_private());
+        // 0b10 is reserved
+        assert_eq!(address(0x80).random_kind(), None);
+        assert!(!address(0xBF).is_static_random());
+    }
+}
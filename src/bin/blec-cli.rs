@@ -0,0 +1,368 @@
+//! Command line tool to scan for devices, browse their services and read, write or listen to characteristics
+
+use blec::{
+    blocking, BleAddress, BleDeviceId, BleError, ConnectTarget, DeviceFilter, DiscoverFilter,
+    DEFAULT_CONNECT_TIMEOUT,
+};
+use clap::{Args, Parser, Subcommand};
+use std::io::{self, BufRead, Write};
+use std::process::ExitCode;
+use std::sync::mpsc;
+use std::time::Instant;
+use uuid::Uuid;
+
+#[derive(Parser)]
+#[command(name = "blec-cli", version, about)]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Scan for devices
+    Scan(ScanArgs),
+    /// List the services and characteristics of a device
+    Services {
+        /// address or id of the device
+        target: String,
+        #[arg(long)]
+        json: bool,
+    },
+    /// Read a characteristic
+    Read {
+        target: String,
+        charac: Uuid,
+        /// print the value as text instead of hex
+        #[arg(long)]
+        utf8: bool,
+    },
+    /// Write to a characteristic
+    Write {
+        target: String,
+        charac: Uuid,
+        /// hex encoded bytes, or text with --utf8
+        data: String,
+        #[arg(long)]
+        utf8: bool,
+    },
+    /// Print notifications of a characteristic until the device disconnects
+    Listen {
+        target: String,
+        charac: Uuid,
+        #[arg(long)]
+        utf8: bool,
+    },
+    /// Interactive shell keeping the connection between commands
+    Shell,
+}
+
+#[derive(Args)]
+struct ScanArgs {
+    /// scan duration in milliseconds
+    #[arg(short, long, default_value_t = 5000)]
+    timeout: u64,
+    /// exact local name
+    #[arg(long)]
+    name: Option<String>,
+    /// prefix of the local name
+    #[arg(long)]
+    name_prefix: Option<String>,
+    /// advertised service, can be given multiple times
+    #[arg(long = "service")]
+    services: Vec<Uuid>,
+    #[arg(long, allow_negative_numbers = true)]
+    min_rssi: Option<i16>,
+    #[arg(long)]
+    json: bool,
+}
+
+/// Commands of the interactive shell, they use the device connected with `connect`
+#[derive(Parser)]
+#[command(multicall = true)]
+enum ShellCommand {
+    Scan(ScanArgs),
+    Connect {
+        target: String,
+    },
+    Disconnect,
+    Services {
+        #[arg(long)]
+        json: bool,
+    },
+    Read {
+        charac: Uuid,
+        #[arg(long)]
+        utf8: bool,
+    },
+    Write {
+        charac: Uuid,
+        data: String,
+        #[arg(long)]
+        utf8: bool,
+    },
+    /// Print notifications in the background
+    Listen {
+        charac: Uuid,
+        #[arg(long)]
+        utf8: bool,
+    },
+    #[command(alias = "exit")]
+    Quit,
+}
+
+#[derive(Debug, thiserror::Error)]
+enum CliError {
+    #[error(transparent)]
+    Ble(#[from] BleError),
+    #[error("{0}")]
+    InvalidData(String),
+    #[error(transparent)]
+    Io(#[from] io::Error),
+    #[error(transparent)]
+    Json(#[from] serde_json::Error),
+}
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+    match blec::init()
+        .map_err(CliError::from)
+        .and_then(|_| run(cli.command))
+    {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("error: {e}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run(command: Command) -> Result<(), CliError> {
+    match command {
+        Command::Scan(args) => scan(&args),
+        Command::Services { target, json } => {
+            connect(&target)?;
+            let result = services(json);
+            blocking::disconnect()?;
+            result
+        }
+        Command::Read {
+            target,
+            charac,
+            utf8,
+        } => {
+            connect(&target)?;
+            let result = read(charac, utf8);
+            blocking::disconnect()?;
+            result
+        }
+        Command::Write {
+            target,
+            charac,
+            data,
+            utf8,
+        } => {
+            let data = parse_data(&data, utf8)?;
+            connect(&target)?;
+            let result = blocking::send_data(charac, data);
+            blocking::disconnect()?;
+            Ok(result?)
+        }
+        Command::Listen {
+            target,
+            charac,
+            utf8,
+        } => {
+            let (tx, rx) = mpsc::channel();
+            let target = parse_target(&target)?;
+            blocking::connect_all(
+                target,
+                Some(move |_, reason| {
+                    let _ = tx.send(reason);
+                }),
+            )?;
+            listen(charac, utf8)?;
+            // runs until the device disconnects or the process is stopped
+            if let Ok(reason) = rx.recv() {
+                eprintln!("disconnected: {reason:?}");
+            }
+            Ok(())
+        }
+        Command::Shell => shell(),
+    }
+}
+
+fn shell() -> Result<(), CliError> {
+    let stdin = io::stdin();
+    let mut lines = stdin.lock().lines();
+    loop {
+        print!("blec> ");
+        io::stdout().flush()?;
+        let Some(line) = lines.next().transpose()? else {
+            break;
+        };
+        let args = line.split_whitespace();
+        let command = match ShellCommand::try_parse_from(args) {
+            Ok(command) => command,
+            Err(e) => {
+                // also prints the help for an empty line
+                let _ = e.print();
+                continue;
+            }
+        };
+        let result = match command {
+            ShellCommand::Scan(args) => scan(&args),
+            ShellCommand::Connect { target } => connect(&target),
+            ShellCommand::Disconnect => blocking::disconnect().map_err(Into::into),
+            ShellCommand::Services { json } => services(json),
+            ShellCommand::Read { charac, utf8 } => read(charac, utf8),
+            ShellCommand::Write { charac, data, utf8 } => parse_data(&data, utf8)
+                .and_then(|data| blocking::send_data(charac, data).map_err(Into::into)),
+            ShellCommand::Listen { charac, utf8 } => listen(charac, utf8),
+            ShellCommand::Quit => break,
+        };
+        if let Err(e) = result {
+            eprintln!("error: {e}");
+        }
+    }
+    if blocking::is_connected()? {
+        blocking::disconnect()?;
+    }
+    Ok(())
+}
+
+fn scan(args: &ScanArgs) -> Result<(), CliError> {
+    let filter = DiscoverFilter {
+        name: args.name.clone(),
+        name_prefix: args.name_prefix.clone(),
+        services: args.services.clone(),
+        min_rssi: args.min_rssi,
+    };
+    let devices: Vec<_> = blocking::discover(args.timeout)?
+        .into_iter()
+        .filter(|d| filter.matches(d))
+        .collect();
+    if args.json {
+        println!("{}", serde_json::to_string_pretty(&devices)?);
+        return Ok(());
+    }
+    for device in devices {
+        let rssi = device.rssi.map(|r| r.to_string()).unwrap_or_default();
+        println!(
+            "{}  {:>4}  {}  ({})",
+            device.address, rssi, device.name, device.id
+        );
+    }
+    Ok(())
+}
+
+fn connect(target: &str) -> Result<(), CliError> {
+    let target = parse_target(target)?;
+    blocking::connect_all(target, None::<fn(_, _)>)?;
+    Ok(())
+}
+
+fn services(json: bool) -> Result<(), CliError> {
+    let services = blocking::services()?;
+    if json {
+        println!("{}", serde_json::to_string_pretty(&services)?);
+        return Ok(());
+    }
+    for service in services {
+        println!("service {}", service.uuid);
+        for charac in service.characteristics {
+            println!("  charac {}  [{}]", charac.uuid, charac.properties);
+        }
+    }
+    Ok(())
+}
+
+fn read(charac: Uuid, utf8: bool) -> Result<(), CliError> {
+    let data = blocking::recv_data(charac)?;
+    println!("{}", format_data(&data, utf8));
+    Ok(())
+}
+
+fn listen(charac: Uuid, utf8: bool) -> Result<(), CliError> {
+    let start = Instant::now();
+    blocking::subscribe(charac, move |data: &[u8]| {
+        let elapsed = start.elapsed().as_secs_f64();
+        println!("[{elapsed:9.3}] {charac}: {}", format_data(data, utf8));
+    })?;
+    Ok(())
+}
+
+/// Accepts an address or the id printed by `scan`.
+/// A new process doesn't know any devices yet, so ids are scanned for.
+fn parse_target(target: &str) -> Result<ConnectTarget, CliError> {
+    resolve_target(target, |id| {
+        blocking::scan_device_id(id, DEFAULT_CONNECT_TIMEOUT)
+    })
+}
+
+fn resolve_target(
+    target: &str,
+    lookup_id: impl FnOnce(String) -> Result<BleDeviceId, BleError>,
+) -> Result<ConnectTarget, CliError> {
+    match target.parse::<BleAddress>() {
+        Ok(address) => Ok(address.into()),
+        Err(_) => Ok(lookup_id(target.to_string())?.into()),
+    }
+}
+
+fn parse_data(data: &str, utf8: bool) -> Result<Vec<u8>, CliError> {
+    if utf8 {
+        return Ok(data.as_bytes().to_vec());
+    }
+    let hex = data.trim_start_matches("0x");
+    // from_str_radix alone would accept a sign like `+f`
+    if !hex.bytes().all(|b| b.is_ascii_hexdigit()) || !hex.len().is_multiple_of(2) {
+        return Err(CliError::InvalidData(format!("invalid hex data {data}")));
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| {
+            u8::from_str_radix(&hex[i..i + 2], 16)
+                .map_err(|_| CliError::InvalidData(format!("invalid hex data {data}")))
+        })
+        .collect()
+}
+
+fn format_data(data: &[u8], utf8: bool) -> String {
+    if utf8 {
+        return String::from_utf8_lossy(data).into_owned();
+    }
+    data.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_hex_data() {
+        assert_eq!(parse_data("0x01ff", false).unwrap(), vec![0x01, 0xff]);
+        assert!(parse_data("", false).unwrap().is_empty());
+        for invalid in ["+f", "+1+2", "-1", "0g", "abc", "äb"] {
+            assert!(parse_data(invalid, false).is_err(), "{invalid}");
+        }
+    }
+
+    #[test]
+    fn looks_up_ids_only_for_non_addresses() {
+        let target = resolve_target("AA:BB:CC:DD:EE:FF", |_| panic!("looked up an address"));
+        assert!(matches!(target, Ok(ConnectTarget::Address(_))));
+
+        let id = "/org/bluez/hci0/dev_AA_BB_CC_DD_EE_FF";
+        let mut looked_up = None;
+        let target = resolve_target(id, |id| {
+            looked_up = Some(id.clone());
+            Err(BleError::UnknownPeripheral(id))
+        });
+        assert!(matches!(
+            target,
+            Err(CliError::Ble(BleError::UnknownPeripheral(_)))
+        ));
+        assert_eq!(looked_up.as_deref(), Some(id));
+    }
+}
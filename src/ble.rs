@@ -1,42 +1,175 @@
+use crate::adapter;
+#[cfg(feature = "record")]
+use crate::record::{self, RecordedOp};
 use crate::setup::{self, RUNTIME};
-use crate::{block_on, spawn, BleAddress, BleDevice};
+use crate::{
+    block_on, spawn, AdapterInfo, AdapterSelector, AdapterState, BleDevice, BleDeviceId, BleEvent,
+    BleService, CharacCodec, ConnectStrategy, ConnectTarget, DeviceFilter, DisconnectReason,
+    InitConfig,
+};
 use crate::{handler::BleHandler, BleError};
-use futures::{Future, StreamExt};
-use once_cell::sync::OnceCell;
+use btleplug::api::CentralEvent;
+use futures::{Future, Stream, StreamExt};
+#[cfg(not(feature = "tracing"))]
+use log::{debug, error};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
 use tokio::sync::{mpsc, Mutex};
+use tokio::task::AbortHandle;
+#[cfg(feature = "tracing")]
+use tracing::{debug, error};
 use uuid::Uuid;
 
-static HANDLER: OnceCell<Mutex<BleHandler>> = OnceCell::new();
+static HANDLER: RwLock<Option<Arc<Mutex<BleHandler>>>> = RwLock::new(None);
+/// serializes init and deinit
+static INIT: std::sync::Mutex<()> = std::sync::Mutex::new(());
+static EVENT_LOOP: std::sync::Mutex<Option<AbortHandle>> = std::sync::Mutex::new(None);
+/// tasks running registered callbacks, aborted by deinit
+static CALLBACK_TASKS: std::sync::Mutex<Vec<AbortHandle>> = std::sync::Mutex::new(vec![]);
+/// number of running event loops, an aborted loop may still be dropped after its replacement started
+static EVENT_LOOPS_RUNNING: AtomicUsize = AtomicUsize::new(0);
+
+/// time to wait before acquiring the event stream again after it failed or ended
+const EVENT_LOOP_RESTART_DELAY: Duration = Duration::from_secs(1);
 
 /// The init() function must be called before anything else.
-/// At the moment the developer has to make sure it is only called once.
+/// Calling it again does nothing until [deinit] is called.
 pub fn init() -> Result<(), BleError> {
-    // crate the runtime include architecture specific differences
-    setup::create_runtime()?;
-    let rt = RUNTIME.get().ok_or(BleError::RuntimeNotInitialized)?;
-    HANDLER
-        .set(Mutex::new(rt.block_on(BleHandler::new())?))
-        .map_err(|_| BleError::HandlerAlreadyInitialized)?;
+    init_with_config(InitConfig::default())
+}
+
+/// Same as [init] but allows to configure blec, e.g. to select the adapter to use.
+/// The runtime is only created on the first call and reused after [deinit],
+/// so the runtime config of later calls is ignored.
+pub fn init_with_config(config: InitConfig) -> Result<(), BleError> {
+    let _init = INIT.lock().unwrap_or_else(|e| e.into_inner());
+    if HANDLER.read().unwrap_or_else(|e| e.into_inner()).is_some() {
+        return Ok(());
+    }
+    if RUNTIME.get().is_none() {
+        // crate the runtime include architecture specific differences
+        setup::create_runtime(config.runtime)?;
+    }
+    setup::check_setup_error()?;
+    let handler = block_on(BleHandler::new(&config.adapter))??;
+    *HANDLER.write().unwrap_or_else(|e| e.into_inner()) = Some(Arc::new(Mutex::new(handler)));
     // start a loop running in the background for handling ble events
-    rt.spawn(event_loop());
+    start_event_loop()
+}
+
+/// Disconnects, stops all background tasks and drops the handler, so [init] can be called again.
+/// Registered callbacks are removed.
+pub fn deinit() -> Result<(), BleError> {
+    let _init = INIT.lock().unwrap_or_else(|e| e.into_inner());
+    if HANDLER.read().unwrap_or_else(|e| e.into_inner()).is_none() {
+        return Ok(());
+    }
+    // checked before tearing anything down
+    if !RUNTIME.get().is_some_and(|rt| rt.can_block()) {
+        return Err(BleError::BlockingInAsyncContext);
+    }
+    let Some(handler) = HANDLER.write().unwrap_or_else(|e| e.into_inner()).take() else {
+        return Ok(());
+    };
+    if let Some(handle) = EVENT_LOOP.lock().unwrap_or_else(|e| e.into_inner()).take() {
+        handle.abort();
+    }
+    for handle in CALLBACK_TASKS
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .drain(..)
+    {
+        handle.abort();
+    }
+    adapter::set_state(AdapterState::Unknown);
+    setup::clear_setup_error();
+    // disconnecting also stops the notification task
+    block_on(async move {
+        let mut handler = handler.lock().await;
+        handler.disconnect(DisconnectReason::UserInitiated).await
+    })?
+}
+
+/// (re)starts the event loop, needed whenever the adapter changes
+fn start_event_loop() -> Result<(), BleError> {
+    let rt = RUNTIME.get().ok_or(BleError::RuntimeNotInitialized)?;
+    let mut event_loop_handle = EVENT_LOOP.lock().unwrap_or_else(|e| e.into_inner());
+    if let Some(handle) = event_loop_handle.take() {
+        handle.abort();
+    }
+    *event_loop_handle = Some(rt.spawn(supervise_event_loop()).abort_handle());
     Ok(())
 }
 
+/// Keeps the event loop alive, errors are logged and reported as [BleEvent::EventLoopError]
+async fn supervise_event_loop() {
+    loop {
+        match event_loop().await {
+            Ok(()) => debug!("event stream ended, acquiring it again"),
+            Err(e) => {
+                error!("event loop failed: {e}");
+                crate::events::emit(BleEvent::EventLoopError(e.to_string()));
+            }
+        }
+        tokio::time::sleep(EVENT_LOOP_RESTART_DELAY).await;
+    }
+}
+
+/// Counts the event loop as running until dropped
+struct RunningGuard;
+
+impl RunningGuard {
+    fn new() -> Self {
+        EVENT_LOOPS_RUNNING.fetch_add(1, Ordering::SeqCst);
+        Self
+    }
+}
+
+impl Drop for RunningGuard {
+    fn drop(&mut self) {
+        EVENT_LOOPS_RUNNING.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
 async fn event_loop() -> Result<(), BleError> {
-    let handler = get_handler();
-    let mut events = handler.lock().await.get_event_stream().await?;
-    while let Some(event) = events.next().await {
-        handler.lock().await.handle_event(event).await?;
+    let handler = get_handler()?;
+    let (mut event_stream, adapter) = {
+        let handler = handler.lock().await;
+        (handler.get_event_stream().await?, handler.adapter())
+    };
+    let _running = RunningGuard::new();
+    while let Some(event) = event_stream.next().await {
+        if let CentralEvent::StateUpdate(state) = &event {
+            // updated before locking the handler so running scans are paused immediately
+            adapter::set_state(state.clone().into());
+        }
+        if crate::events::has_subscribers() {
+            if let Some(ble_event) = crate::events::translate(&adapter, &event).await {
+                crate::events::emit(ble_event);
+            }
+        }
+        if let Err(e) = handler.lock().await.handle_event(event).await {
+            // a failed event must not stop handling the following ones
+            error!("handling event failed: {e}");
+            crate::events::emit(BleEvent::EventLoopError(e.to_string()));
+        }
     }
     Ok(())
 }
 
-fn get_handler() -> &'static Mutex<BleHandler> {
-    let handler = HANDLER
-        .get()
+/// Returns true while the background loop handling adapter events is running.
+/// The loop is restarted automatically if the event stream fails or ends.
+pub fn event_loop_running() -> bool {
+    EVENT_LOOPS_RUNNING.load(Ordering::SeqCst) > 0
+}
+
+fn get_handler() -> Result<Arc<Mutex<BleHandler>>, BleError> {
+    HANDLER
+        .read()
+        .unwrap_or_else(|e| e.into_inner())
+        .clone()
         .ok_or(BleError::HandlerNotInitialized)
-        .unwrap();
-    handler
 }
 
 async fn run_on_runtime<F, O>(f: F) -> Result<O, BleError>
@@ -44,82 +177,435 @@ where
     F: Future<Output = Result<O, BleError>> + Send + 'static,
     O: Send + 'static,
 {
+    setup::check_setup_error()?;
     let rt = RUNTIME.get().ok_or(BleError::RuntimeNotInitialized)?;
-    rt.spawn(f).await.map_err(|e| BleError::JoinError(e))?
+    // the operation runs in the span of the caller
+    #[cfg(feature = "tracing")]
+    let f = tracing::Instrument::in_current_span(f);
+    let result = rt.spawn(f).await.map_err(BleError::JoinError)?;
+    #[cfg(feature = "metrics")]
+    if let Err(e) = &result {
+        crate::metrics::error(e);
+    }
+    result
 }
 
+/// Lists the bluetooth adapters available on the system
+pub async fn list_adapters() -> Result<Vec<AdapterInfo>, BleError> {
+    run_on_runtime(async move {
+        let handler = get_handler()?.lock_owned().await;
+        handler.list_adapters().await
+    })
+    .await
+}
+
+/// Returns information about the adapter currently in use
+pub async fn current_adapter() -> Result<AdapterInfo, BleError> {
+    run_on_runtime(async move {
+        let handler = get_handler()?.lock_owned().await;
+        Ok(handler.adapter_info().clone())
+    })
+    .await
+}
+
+/// Stream of adapter events like discovered devices, advertisements and connection changes.
+/// Only events emitted after the call are received, old events are dropped if the stream is not polled fast enough.
+pub fn events() -> impl Stream<Item = BleEvent> {
+    crate::events::subscribe()
+}
+
+/// Returns the last known state of the adapter
+pub fn adapter_state() -> AdapterState {
+    adapter::current_state()
+}
+
+/// Registers a callback that is called whenever the adapter state changes.
+/// While the adapter is not available scans are paused and other operations fail with [BleError::AdapterOff].
+pub fn on_adapter_state(
+    callback: impl Fn(AdapterState) + Send + Sync + 'static,
+) -> Result<(), BleError> {
+    let rt = RUNTIME.get().ok_or(BleError::RuntimeNotInitialized)?;
+    let mut states = adapter::state_receiver();
+    let callback = std::sync::Arc::new(callback);
+    let task = rt.spawn(async move {
+        while states.changed().await.is_ok() {
+            let state = *states.borrow_and_update();
+            let cb = callback.clone();
+            tokio::task::spawn_blocking(move || cb(state));
+        }
+    });
+    CALLBACK_TASKS
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .push(task.abort_handle());
+    Ok(())
+}
+
+/// Switches to another adapter at runtime.
+/// The connected device is disconnected before switching.
+pub async fn select_adapter(selector: AdapterSelector) -> Result<AdapterInfo, BleError> {
+    let info = run_on_runtime(async move {
+        let mut handler = get_handler()?.lock_owned().await;
+        handler.select_adapter(&selector).await
+    })
+    .await?;
+    // the event stream belongs to the old adapter
+    start_event_loop()?;
+    Ok(info)
+}
+
+/// Connects to the device with the given address or [BleDeviceId](crate::BleDeviceId) using the default [ConnectStrategy].
+/// The device is scanned for until it is found or [DEFAULT_CONNECT_TIMEOUT](crate::DEFAULT_CONNECT_TIMEOUT) elapsed.
 pub async fn connect(
-    addr: BleAddress,
+    target: impl Into<ConnectTarget>,
+    service: Uuid,
+    characs: Vec<Uuid>,
+    on_disconnect: Option<impl FnOnce(BleDevice, DisconnectReason) + Send + 'static>,
+) -> Result<(), BleError> {
+    connect_with_strategy(
+        target,
+        service,
+        characs,
+        on_disconnect,
+        ConnectStrategy::default(),
+    )
+    .await
+}
+
+/// Connects to the device with the given address or [BleDeviceId](crate::BleDeviceId).
+/// The strategy defines if and how long to scan for the device.
+pub async fn connect_with_strategy(
+    target: impl Into<ConnectTarget>,
     service: Uuid,
     characs: Vec<Uuid>,
-    on_disconnect: Option<impl Fn() + Send + 'static>,
+    on_disconnect: Option<impl FnOnce(BleDevice, DisconnectReason) + Send + 'static>,
+    strategy: ConnectStrategy,
+) -> Result<(), BleError> {
+    let target = target.into();
+    #[cfg(feature = "record")]
+    if let Some(replay) = record::replayer() {
+        return replay
+            .connect(Some(target), boxed(on_disconnect))
+            .await
+            .map(drop);
+    }
+    run_on_runtime(async move {
+        let mut handler = get_handler()?.lock_owned().await;
+        #[cfg(feature = "record")]
+        let recorded_target = target.clone();
+        let result = handler
+            .connect(target, Some(service), characs, on_disconnect, strategy)
+            .await;
+        #[cfg(feature = "record")]
+        record_connect(&handler, Some(recorded_target), &result, None).await;
+        result
+    })
+    .await
+}
+
+/// Connects like [connect] but makes all characteristics of all services available,
+/// e.g. to browse the device with [services] or to use characteristics not known in advance.
+pub async fn connect_all(
+    target: impl Into<ConnectTarget>,
+    on_disconnect: Option<impl FnOnce(BleDevice, DisconnectReason) + Send + 'static>,
 ) -> Result<(), BleError> {
+    let target = target.into();
+    #[cfg(feature = "record")]
+    if let Some(replay) = record::replayer() {
+        return replay
+            .connect(Some(target), boxed(on_disconnect))
+            .await
+            .map(drop);
+    }
+    run_on_runtime(async move {
+        let mut handler = get_handler()?.lock_owned().await;
+        #[cfg(feature = "record")]
+        let recorded_target = target.clone();
+        let result = handler
+            .connect(
+                target,
+                None,
+                vec![],
+                on_disconnect,
+                ConnectStrategy::default(),
+            )
+            .await;
+        #[cfg(feature = "record")]
+        record_connect(&handler, Some(recorded_target), &result, None).await;
+        result
+    })
+    .await
+}
+
+/// Scans for up to [timeout] milliseconds and connects to the first device matching the filter.
+/// Returns the device that was connected.
+/// Fails with [BleError::AlreadyConnected] if the connected device matches the filter.
+pub async fn connect_matching(
+    filter: impl DeviceFilter,
+    service: Uuid,
+    characs: Vec<Uuid>,
+    timeout: u64,
+) -> Result<BleDevice, BleError> {
+    #[cfg(feature = "record")]
+    if let Some(replay) = record::replayer() {
+        return replay_matching(&replay).await;
+    }
     run_on_runtime(async move {
-        let mut handler = get_handler().lock().await;
-        handler.connect(addr, service, characs, on_disconnect).await
+        let mut handler = get_handler()?.lock_owned().await;
+        let result = handler
+            .connect_matching(filter, service, characs, timeout, false)
+            .await;
+        #[cfg(feature = "record")]
+        record_connect(&handler, None, &result, result.as_ref().ok()).await;
+        result
+    })
+    .await
+}
+
+/// Scans for [timeout] milliseconds and connects to the device matching the filter with the strongest signal.
+/// Returns the device that was connected.
+pub async fn connect_strongest(
+    filter: impl DeviceFilter,
+    service: Uuid,
+    characs: Vec<Uuid>,
+    timeout: u64,
+) -> Result<BleDevice, BleError> {
+    #[cfg(feature = "record")]
+    if let Some(replay) = record::replayer() {
+        return replay_matching(&replay).await;
+    }
+    run_on_runtime(async move {
+        let mut handler = get_handler()?.lock_owned().await;
+        let result = handler
+            .connect_matching(filter, service, characs, timeout, true)
+            .await;
+        #[cfg(feature = "record")]
+        record_connect(&handler, None, &result, result.as_ref().ok()).await;
+        result
     })
     .await
 }
 
 pub async fn disconnect() -> Result<(), BleError> {
+    #[cfg(feature = "record")]
+    if let Some(replay) = record::replayer() {
+        return replay.disconnect().await;
+    }
+    run_on_runtime(async move {
+        let mut handler = get_handler()?.lock_owned().await;
+        // only recorded here, disconnects done internally by blec are repeated by the replay itself
+        #[cfg(feature = "record")]
+        if handler.has_connection() {
+            record::record(|| RecordedOp::Disconnected {
+                reason: DisconnectReason::UserInitiated,
+            });
+        }
+        handler.disconnect(DisconnectReason::UserInitiated).await
+    })
+    .await
+}
+
+/// Sets the disconnect callback of the current connection,
+/// e.g. to install it only after the connected device was checked
+#[cfg(feature = "derive")]
+pub(crate) async fn set_on_disconnect(
+    on_disconnect: impl FnOnce(BleDevice, DisconnectReason) + Send + 'static,
+) -> Result<(), BleError> {
+    #[cfg(feature = "record")]
+    if let Some(replay) = record::replayer() {
+        return replay.set_on_disconnect(Box::new(on_disconnect));
+    }
     run_on_runtime(async move {
-        let mut handler = get_handler().lock().await;
-        handler.disconnect().await
+        let mut handler = get_handler()?.lock_owned().await;
+        handler.set_on_disconnect(on_disconnect).await
     })
     .await
 }
 
-pub fn discover(sink: mpsc::Sender<Vec<BleDevice>>, timeout: u64) -> Result<(), BleError> {
+/// Looks up a discovered or known device by the string representation of its [BleDeviceId],
+/// e.g. to reconnect with an id stored as string
+pub async fn device_id(id: String) -> Result<BleDeviceId, BleError> {
+    #[cfg(feature = "record")]
+    if let Some(replay) = record::replayer() {
+        return replay.device_id(&id);
+    }
+    run_on_runtime(async move {
+        let handler = get_handler()?.lock_owned().await;
+        handler.device_id(&id).await
+    })
+    .await
+}
+
+/// Same as [device_id] but scans for up to [timeout] milliseconds if the id is unknown,
+/// e.g. to reconnect to an id persisted before the app restarted
+pub async fn scan_device_id(id: String, timeout: u64) -> Result<BleDeviceId, BleError> {
+    #[cfg(feature = "record")]
+    if let Some(replay) = record::replayer() {
+        return replay.device_id(&id);
+    }
+    run_on_runtime(async move {
+        let handler = get_handler()?.lock_owned().await;
+        handler.scan_device_id(&id, timeout).await
+    })
+    .await
+}
+
+/// Parses an address or falls back to looking up the device id.
+/// Unknown ids are scanned for up to [DEFAULT_CONNECT_TIMEOUT](crate::DEFAULT_CONNECT_TIMEOUT).
+#[cfg(any(feature = "frb", feature = "uniffi", feature = "python"))]
+pub(crate) async fn parse_target(target: String) -> Result<ConnectTarget, BleError> {
+    if let Ok(address) = target.parse::<crate::BleAddress>() {
+        return Ok(ConnectTarget::Address(address));
+    }
+    let id = scan_device_id(target, crate::DEFAULT_CONNECT_TIMEOUT).await?;
+    Ok(ConnectTarget::Id(id))
+}
+
+#[cfg(any(feature = "frb", feature = "uniffi", feature = "python"))]
+pub(crate) fn parse_uuids(uuids: &[String]) -> Result<Vec<Uuid>, BleError> {
+    Ok(uuids
+        .iter()
+        .map(|uuid| Uuid::parse_str(uuid))
+        .collect::<Result<_, _>>()?)
+}
+
+/// Scans for [timeout] milliseconds and periodically sends the discovered devices to the sink.
+/// Errors during the scan are sent to the sink as well.
+pub fn discover(
+    sink: mpsc::Sender<Result<Vec<BleDevice>, BleError>>,
+    timeout: u64,
+) -> Result<(), BleError> {
     spawn(async move {
-        let mut handler = get_handler().lock().await;
-        handler.discover(Some(sink), timeout).await
+        #[cfg(feature = "record")]
+        if let Some(replay) = record::replayer() {
+            let _ = sink.send(replay.discover().await).await;
+            return;
+        }
+        let result = async {
+            let mut handler = get_handler()?.lock_owned().await;
+            let result = handler.discover(Some(sink.clone()), timeout).await;
+            #[cfg(feature = "record")]
+            record::record(|| RecordedOp::Discover {
+                result: record::recorded_devices(&result),
+            });
+            result
+        }
+        .await;
+        if let Err(e) = result {
+            #[cfg(feature = "metrics")]
+            crate::metrics::error(&e);
+            // fails only if the receiver is gone, then there is nobody to report to
+            let _ = sink.send(Err(e)).await;
+        }
     })
 }
 
 pub async fn discover_async(timeout: u64) -> Result<Vec<BleDevice>, BleError> {
-    let discovered = run_on_runtime(async move {
-        let mut handler = get_handler().lock().await;
-        handler.discover(None, timeout).await
-    })
-    .await?;
+    #[cfg(feature = "record")]
+    if let Some(replay) = record::replayer() {
+        return replay.discover().await;
+    }
+    let discovered = run_on_runtime(discover_devices(timeout)).await?;
     Ok(discovered)
 }
 
 pub fn discover_blocking(timeout: u64) -> Result<Vec<BleDevice>, BleError> {
-    let discovered = block_on(async move {
-        let mut handler = get_handler().lock().await;
-        handler.discover(None, timeout).await
-    })??;
-    Ok(discovered)
+    #[cfg(feature = "record")]
+    if let Some(replay) = record::replayer() {
+        return block_on(async move { replay.discover().await })?;
+    }
+    let discovered = block_on(discover_devices(timeout))?;
+    #[cfg(feature = "metrics")]
+    if let Err(e) = &discovered {
+        crate::metrics::error(e);
+    }
+    discovered
+}
+
+async fn discover_devices(timeout: u64) -> Result<Vec<BleDevice>, BleError> {
+    let mut handler = get_handler()?.lock_owned().await;
+    let result = handler.discover(None, timeout).await;
+    #[cfg(feature = "record")]
+    record::record(|| RecordedOp::Discover {
+        result: record::recorded_devices(&result),
+    });
+    result
 }
 
 pub async fn send_data(charac: Uuid, data: Vec<u8>) -> Result<(), BleError> {
+    #[cfg(feature = "record")]
+    if let Some(replay) = record::replayer() {
+        return replay.send_data(charac, data).await;
+    }
     run_on_runtime(async move {
-        let mut handler = get_handler().lock().await;
-        handler.send_data(charac, &data).await
+        let mut handler = get_handler()?.lock_owned().await;
+        let result = handler.send_data(charac, &data).await;
+        #[cfg(feature = "record")]
+        record::record(|| RecordedOp::Write {
+            charac,
+            data,
+            result: record::recorded(&result),
+        });
+        result
     })
     .await
 }
 
 pub async fn recv_data(charac: Uuid) -> Result<Vec<u8>, BleError> {
+    #[cfg(feature = "record")]
+    if let Some(replay) = record::replayer() {
+        return replay.recv_data(charac).await;
+    }
     run_on_runtime(async move {
-        let mut handler = get_handler().lock().await;
-        handler.recv_data(charac).await
+        let mut handler = get_handler()?.lock_owned().await;
+        let result = handler.recv_data(charac).await;
+        #[cfg(feature = "record")]
+        record::record(|| RecordedOp::Read {
+            charac,
+            result: record::recorded(&result),
+        });
+        result
+    })
+    .await
+}
+
+/// Services and characteristics of the connected device
+pub async fn services() -> Result<Vec<BleService>, BleError> {
+    #[cfg(feature = "record")]
+    if let Some(replay) = record::replayer() {
+        return replay.services().await;
+    }
+    run_on_runtime(async move {
+        let mut handler = get_handler()?.lock_owned().await;
+        let result = handler.services().await;
+        #[cfg(feature = "record")]
+        record::record(|| RecordedOp::Services {
+            result: record::recorded(&result),
+        });
+        result
     })
     .await
 }
 
 pub async fn is_connected() -> Result<bool, BleError> {
+    #[cfg(feature = "record")]
+    if let Some(replay) = record::replayer() {
+        return Ok(replay.is_connected());
+    }
     run_on_runtime(async move {
-        let handler = get_handler().lock().await;
+        let handler = get_handler()?.lock_owned().await;
         handler.check_connected().await
     })
     .await
 }
 
 pub async fn connected_device() -> Result<BleDevice, BleError> {
+    #[cfg(feature = "record")]
+    if let Some(replay) = record::replayer() {
+        return replay.connected_device();
+    }
     run_on_runtime(async move {
-        let handler = get_handler().lock().await;
+        let handler = get_handler()?.lock_owned().await;
         handler.connected_device().await
     })
     .await
@@ -129,9 +615,81 @@ pub async fn subscribe(
     charac: Uuid,
     callback: impl Fn(&[u8]) + Send + Sync + 'static,
 ) -> Result<(), BleError> {
+    #[cfg(feature = "record")]
+    if let Some(replay) = record::replayer() {
+        return replay.subscribe(charac, callback).await;
+    }
     run_on_runtime(async move {
-        let mut handler = get_handler().lock().await;
-        handler.subscribe(charac, callback).await
+        let mut handler = get_handler()?.lock_owned().await;
+        let result = handler.subscribe(charac, callback).await;
+        #[cfg(feature = "record")]
+        record::record(|| RecordedOp::Subscribe {
+            charac,
+            result: record::recorded(&result),
+        });
+        result
+    })
+    .await
+}
+
+/// Reads the characteristic and decodes its value, e.g. `read::<u16>(charac)`.
+/// Fails with [BleError::WrongData] if the value can't be decoded.
+pub async fn read<T: CharacCodec>(charac: Uuid) -> Result<T, BleError> {
+    let data = recv_data(charac).await?;
+    T::decode(&data).map_err(|source| BleError::WrongData { charac, source })
+}
+
+/// Encodes the value and writes it to the characteristic
+pub async fn write<T: CharacCodec>(charac: Uuid, value: &T) -> Result<(), BleError> {
+    send_data(charac, value.encode()).await
+}
+
+/// Like [subscribe] but decodes every notification,
+/// values that can't be decoded are passed to the callback as [BleError::WrongData]
+pub async fn subscribe_typed<T: CharacCodec>(
+    charac: Uuid,
+    callback: impl Fn(Result<T, BleError>) + Send + Sync + 'static,
+) -> Result<(), BleError> {
+    subscribe(charac, move |data: &[u8]| {
+        callback(T::decode(data).map_err(|source| BleError::WrongData { charac, source }))
     })
     .await
 }
+
+#[cfg(feature = "record")]
+fn boxed(
+    on_disconnect: Option<impl FnOnce(BleDevice, DisconnectReason) + Send + 'static>,
+) -> Option<record::DisconnectCallback> {
+    on_disconnect.map(|cb| Box::new(cb) as record::DisconnectCallback)
+}
+
+#[cfg(feature = "record")]
+async fn replay_matching(replay: &record::Replayer) -> Result<BleDevice, BleError> {
+    replay
+        .connect(None, None)
+        .await?
+        .ok_or(BleError::NoMatchingDevice)
+}
+
+/// Records the connect with the connected device, `device` is read from the handler if not given
+#[cfg(feature = "record")]
+async fn record_connect<T>(
+    handler: &BleHandler,
+    target: Option<ConnectTarget>,
+    result: &Result<T, BleError>,
+    device: Option<&BleDevice>,
+) {
+    if !record::is_recording() {
+        return;
+    }
+    let device = match (device, result) {
+        (Some(device), _) => Some(device.into()),
+        (None, Ok(_)) => handler.connected_device().await.ok().map(|d| (&d).into()),
+        (None, Err(_)) => None,
+    };
+    record::record(|| RecordedOp::Connect {
+        target: target.as_ref().map(Into::into),
+        result: result.as_ref().map(drop).map_err(Into::into),
+        device,
+    });
+}
@@ -0,0 +1,129 @@
+//! Blocking versions of the async functions in [ble](crate::ble).
+//! They must not be called from inside an async context and return
+//! [BleError::BlockingInAsyncContext] instead of blocking the runtime.
+
+use crate::{
+    ble, block_on, AdapterInfo, AdapterSelector, BleDevice, BleDeviceId, BleError, BleService,
+    CharacCodec, ConnectStrategy, ConnectTarget, DeviceFilter, DisconnectReason,
+};
+use uuid::Uuid;
+
+pub fn list_adapters() -> Result<Vec<AdapterInfo>, BleError> {
+    block_on(ble::list_adapters())?
+}
+
+pub fn current_adapter() -> Result<AdapterInfo, BleError> {
+    block_on(ble::current_adapter())?
+}
+
+pub fn select_adapter(selector: AdapterSelector) -> Result<AdapterInfo, BleError> {
+    block_on(ble::select_adapter(selector))?
+}
+
+pub fn connect(
+    target: impl Into<ConnectTarget>,
+    service: Uuid,
+    characs: Vec<Uuid>,
+    on_disconnect: Option<impl FnOnce(BleDevice, DisconnectReason) + Send + 'static>,
+) -> Result<(), BleError> {
+    block_on(ble::connect(target, service, characs, on_disconnect))?
+}
+
+pub fn connect_with_strategy(
+    target: impl Into<ConnectTarget>,
+    service: Uuid,
+    characs: Vec<Uuid>,
+    on_disconnect: Option<impl FnOnce(BleDevice, DisconnectReason) + Send + 'static>,
+    strategy: ConnectStrategy,
+) -> Result<(), BleError> {
+    block_on(ble::connect_with_strategy(
+        target,
+        service,
+        characs,
+        on_disconnect,
+        strategy,
+    ))?
+}
+
+pub fn connect_all(
+    target: impl Into<ConnectTarget>,
+    on_disconnect: Option<impl FnOnce(BleDevice, DisconnectReason) + Send + 'static>,
+) -> Result<(), BleError> {
+    block_on(ble::connect_all(target, on_disconnect))?
+}
+
+pub fn connect_matching(
+    filter: impl DeviceFilter,
+    service: Uuid,
+    characs: Vec<Uuid>,
+    timeout: u64,
+) -> Result<BleDevice, BleError> {
+    block_on(ble::connect_matching(filter, service, characs, timeout))?
+}
+
+pub fn connect_strongest(
+    filter: impl DeviceFilter,
+    service: Uuid,
+    characs: Vec<Uuid>,
+    timeout: u64,
+) -> Result<BleDevice, BleError> {
+    block_on(ble::connect_strongest(filter, service, characs, timeout))?
+}
+
+pub fn device_id(id: String) -> Result<BleDeviceId, BleError> {
+    block_on(ble::device_id(id))?
+}
+
+pub fn scan_device_id(id: String, timeout: u64) -> Result<BleDeviceId, BleError> {
+    block_on(ble::scan_device_id(id, timeout))?
+}
+
+pub fn disconnect() -> Result<(), BleError> {
+    block_on(ble::disconnect())?
+}
+
+pub fn discover(timeout: u64) -> Result<Vec<BleDevice>, BleError> {
+    ble::discover_blocking(timeout)
+}
+
+pub fn send_data(charac: Uuid, data: Vec<u8>) -> Result<(), BleError> {
+    block_on(ble::send_data(charac, data))?
+}
+
+pub fn recv_data(charac: Uuid) -> Result<Vec<u8>, BleError> {
+    block_on(ble::recv_data(charac))?
+}
+
+pub fn services() -> Result<Vec<BleService>, BleError> {
+    block_on(ble::services())?
+}
+
+pub fn is_connected() -> Result<bool, BleError> {
+    block_on(ble::is_connected())?
+}
+
+pub fn connected_device() -> Result<BleDevice, BleError> {
+    block_on(ble::connected_device())?
+}
+
+pub fn subscribe(
+    charac: Uuid,
+    callback: impl Fn(&[u8]) + Send + Sync + 'static,
+) -> Result<(), BleError> {
+    block_on(ble::subscribe(charac, callback))?
+}
+
+pub fn read<T: CharacCodec>(charac: Uuid) -> Result<T, BleError> {
+    block_on(ble::read(charac))?
+}
+
+pub fn write<T: CharacCodec>(charac: Uuid, value: &T) -> Result<(), BleError> {
+    block_on(ble::write(charac, value))?
+}
+
+pub fn subscribe_typed<T: CharacCodec>(
+    charac: Uuid,
+    callback: impl Fn(Result<T, BleError>) + Send + Sync + 'static,
+) -> Result<(), BleError> {
+    block_on(ble::subscribe_typed(charac, callback))?
+}
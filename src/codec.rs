@@ -0,0 +1,370 @@
+//! Encoding of characteristic values: little-endian numbers, strings and the IEEE-11073 floats of health profiles.
+
+use std::string::FromUtf8Error;
+use thiserror::Error;
+
+/// Conversion of a value from and to the bytes of a characteristic, used by [read](crate::read),
+/// [write](crate::write) and [subscribe_typed](crate::subscribe_typed).
+///
+/// Numbers are little-endian as in the GATT specifications, wrap them in [BigEndian] otherwise.
+/// `Vec<u8>` passes the raw bytes through.
+pub trait CharacCodec: Sized {
+    fn encode(&self) -> Vec<u8>;
+    fn decode(data: &[u8]) -> Result<Self, CodecError>;
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum CodecError {
+    #[error("expected {expected} bytes, got {actual}")]
+    InvalidLength { expected: usize, actual: usize },
+
+    #[error("invalid utf-8: {0}")]
+    InvalidUtf8(#[from] FromUtf8Error),
+
+    #[error("invalid value: {0}")]
+    InvalidValue(String),
+}
+
+/// Number encoded big-endian
+#[derive(Debug, Clone, Copy, Default, PartialEq, PartialOrd)]
+pub struct BigEndian<T>(pub T);
+
+/// IEEE-11073 16 bit SFLOAT with 12 bit mantissa and 4 bit exponent, used by health profiles.
+/// NaN, NRes and the reserved value are decoded as NaN, values out of range are encoded as infinity.
+#[derive(Debug, Clone, Copy, Default, PartialEq, PartialOrd)]
+pub struct Sfloat(pub f64);
+
+/// IEEE-11073 32 bit FLOAT with 24 bit mantissa and 8 bit exponent
+#[derive(Debug, Clone, Copy, Default, PartialEq, PartialOrd)]
+pub struct Float(pub f64);
+
+fn fixed<const N: usize>(data: &[u8]) -> Result<[u8; N], CodecError> {
+    data.try_into().map_err(|_| CodecError::InvalidLength {
+        expected: N,
+        actual: data.len(),
+    })
+}
+
+macro_rules! impl_number {
+    ($($t:ty),*) => {$(
+        impl CharacCodec for $t {
+            fn encode(&self) -> Vec<u8> {
+                self.to_le_bytes().to_vec()
+            }
+
+            fn decode(data: &[u8]) -> Result<Self, CodecError> {
+                Ok(Self::from_le_bytes(fixed(data)?))
+            }
+        }
+
+        impl From<$t> for BigEndian<$t> {
+            fn from(value: $t) -> Self {
+                Self(value)
+            }
+        }
+
+        impl From<BigEndian<$t>> for $t {
+            fn from(value: BigEndian<$t>) -> Self {
+                value.0
+            }
+        }
+
+        impl CharacCodec for BigEndian<$t> {
+            fn encode(&self) -> Vec<u8> {
+                self.0.to_be_bytes().to_vec()
+            }
+
+            fn decode(data: &[u8]) -> Result<Self, CodecError> {
+                Ok(Self(<$t>::from_be_bytes(fixed(data)?)))
+            }
+        }
+    )*};
+}
+
+impl_number!(u8, u16, u32, u64, i8, i16, i32, i64, f32, f64);
+
+/// One byte, `0` is false and `1` is true
+impl CharacCodec for bool {
+    fn encode(&self) -> Vec<u8> {
+        vec![*self as u8]
+    }
+
+    fn decode(data: &[u8]) -> Result<Self, CodecError> {
+        match fixed(data)? {
+            [0] => Ok(false),
+            [1] => Ok(true),
+            [other] => Err(CodecError::InvalidValue(format!(
+                "{other} is not a boolean"
+            ))),
+        }
+    }
+}
+
+impl CharacCodec for String {
+    fn encode(&self) -> Vec<u8> {
+        self.as_bytes().to_vec()
+    }
+
+    fn decode(data: &[u8]) -> Result<Self, CodecError> {
+        Ok(String::from_utf8(data.to_vec())?)
+    }
+}
+
+impl CharacCodec for Vec<u8> {
+    fn encode(&self) -> Vec<u8> {
+        self.clone()
+    }
+
+    fn decode(data: &[u8]) -> Result<Self, CodecError> {
+        Ok(data.to_vec())
+    }
+}
+
+impl From<f64> for Sfloat {
+    fn from(value: f64) -> Self {
+        Self(value)
+    }
+}
+
+impl From<Sfloat> for f64 {
+    fn from(value: Sfloat) -> Self {
+        value.0
+    }
+}
+
+impl From<f64> for Float {
+    fn from(value: f64) -> Self {
+        Self(value)
+    }
+}
+
+impl From<Float> for f64 {
+    fn from(value: Float) -> Self {
+        value.0
+    }
+}
+
+/// Mantissa and exponent layout of the IEEE-11073 floats
+struct MedicalFloat {
+    bits: u32,
+    mantissa_bits: u32,
+    min_exponent: i32,
+    max_exponent: i32,
+}
+
+const SFLOAT: MedicalFloat = MedicalFloat {
+    bits: 16,
+    mantissa_bits: 12,
+    min_exponent: -8,
+    max_exponent: 7,
+};
+
+const FLOAT: MedicalFloat = MedicalFloat {
+    bits: 32,
+    mantissa_bits: 24,
+    min_exponent: -128,
+    max_exponent: 127,
+};
+
+impl MedicalFloat {
+    /// largest mantissa of a regular value, the ones above are special values
+    fn max_mantissa(&self) -> i64 {
+        (1 << (self.mantissa_bits - 1)) - 3
+    }
+
+    fn pack(&self, mantissa: i64, exponent: i32) -> u32 {
+        let mask = (1u32 << self.mantissa_bits) - 1;
+        ((exponent as u32) << self.mantissa_bits) | (mantissa as u32 & mask)
+    }
+
+    fn encode(&self, value: f64) -> u32 {
+        let max = self.max_mantissa();
+        if value.is_nan() {
+            return self.pack(max + 2, 0);
+        }
+        if value == 0.0 {
+            return 0;
+        }
+        // the smallest exponent the mantissa fits with keeps the most precision
+        for exponent in self.min_exponent..=self.max_exponent {
+            let mantissa = (value / 10f64.powi(exponent)).round();
+            if mantissa.abs() <= max as f64 {
+                return self.pack(mantissa as i64, exponent);
+            }
+        }
+        match value > 0.0 {
+            true => self.pack(max + 1, 0),
+            false => self.pack(-(max + 1), 0),
+        }
+    }
+
+    fn decode(&self, raw: u32) -> f64 {
+        // both fields are signed, shifting them to the top and back extends the sign
+        let exponent = ((raw << (32 - self.bits)) as i32) >> (32 - self.bits + self.mantissa_bits);
+        let mantissa =
+            (((raw << (32 - self.mantissa_bits)) as i32) >> (32 - self.mantissa_bits)) as i64;
+        let max = self.max_mantissa();
+        if exponent == 0 && mantissa.abs() > max {
+            return match mantissa {
+                m if m == max + 1 => f64::INFINITY,
+                m if m == -(max + 1) => f64::NEG_INFINITY,
+                // NaN, NRes and the reserved value
+                _ => f64::NAN,
+            };
+        }
+        // dividing by the exact power of ten keeps values like 36.4 exact, multiplying by 0.1 doesn't
+        match exponent < 0 {
+            true => mantissa as f64 / 10f64.powi(-exponent),
+            false => mantissa as f64 * 10f64.powi(exponent),
+        }
+    }
+}
+
+impl CharacCodec for Sfloat {
+    fn encode(&self) -> Vec<u8> {
+        (SFLOAT.encode(self.0) as u16).to_le_bytes().to_vec()
+    }
+
+    fn decode(data: &[u8]) -> Result<Self, CodecError> {
+        let raw = u16::from_le_bytes(fixed(data)?);
+        Ok(Self(SFLOAT.decode(raw as u32)))
+    }
+}
+
+impl CharacCodec for Float {
+    fn encode(&self) -> Vec<u8> {
+        FLOAT.encode(self.0).to_le_bytes().to_vec()
+    }
+
+    fn decode(data: &[u8]) -> Result<Self, CodecError> {
+        let raw = u32::from_le_bytes(fixed(data)?);
+        Ok(Self(FLOAT.decode(raw)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sfloat(raw: u16) -> f64 {
+        Sfloat::decode(&raw.to_le_bytes()).unwrap().0
+    }
+
+    fn float(raw: u32) -> f64 {
+        Float::decode(&raw.to_le_bytes()).unwrap().0
+    }
+
+    #[test]
+    fn decodes_special_values() {
+        // NaN, NRes and the reserved value
+        for raw in [0x07FF, 0x0800, 0x0801] {
+            assert!(sfloat(raw).is_nan(), "{raw:#06x}");
+        }
+        assert_eq!(sfloat(0x07FE), f64::INFINITY);
+        assert_eq!(sfloat(0x0802), f64::NEG_INFINITY);
+
+        for raw in [0x007F_FFFF, 0x0080_0000, 0x0080_0001] {
+            assert!(float(raw).is_nan(), "{raw:#010x}");
+        }
+        assert_eq!(float(0x007F_FFFE), f64::INFINITY);
+        assert_eq!(float(0x0080_0002), f64::NEG_INFINITY);
+    }
+
+    #[test]
+    fn extends_the_sign_of_both_fields() {
+        // exponent -1, mantissa 364 and -364
+        assert_eq!(sfloat(0xF16C), 36.4);
+        assert_eq!(sfloat(0xFE94), -36.4);
+        // exponent 2, mantissa 5
+        assert_eq!(sfloat(0x2005), 500.0);
+        // exponent -8 is the smallest one
+        assert_eq!(sfloat(0x8001), 1e-8);
+
+        assert_eq!(float(0xFF00_016C), 36.4);
+        assert_eq!(float(0xFFFF_FE94), -36.4);
+        assert_eq!(float(0x0200_0005), 500.0);
+    }
+
+    #[test]
+    fn encodes_with_the_most_precision() {
+        assert_eq!(Sfloat(36.4).encode(), 0xF16Cu16.to_le_bytes());
+        assert_eq!(Sfloat(-36.4).encode(), 0xFE94u16.to_le_bytes());
+        assert_eq!(Sfloat(0.0).encode(), [0, 0]);
+        for value in [36.4, -36.4, 0.5, 2045.0, 98.6] {
+            let encoded = Float(value).encode();
+            assert_eq!(Float::decode(&encoded).unwrap().0, value, "{value}");
+            let encoded = Sfloat(value).encode();
+            assert_eq!(Sfloat::decode(&encoded).unwrap().0, value, "{value}");
+        }
+    }
+
+    #[test]
+    fn encodes_out_of_range_values_as_infinity() {
+        assert_eq!(Sfloat(1e12).encode(), 0x07FEu16.to_le_bytes());
+        assert_eq!(Sfloat(-1e12).encode(), 0x0802u16.to_le_bytes());
+        assert_eq!(Sfloat(f64::INFINITY).encode(), 0x07FEu16.to_le_bytes());
+        assert_eq!(Sfloat(f64::NAN).encode(), 0x07FFu16.to_le_bytes());
+        assert_eq!(Float(1e200).encode(), 0x007F_FFFEu32.to_le_bytes());
+        assert_eq!(Float(-1e200).encode(), 0x0080_0002u32.to_le_bytes());
+        assert_eq!(Float(f64::NAN).encode(), 0x007F_FFFFu32.to_le_bytes());
+    }
+
+    #[test]
+    fn rejects_wrong_lengths() {
+        assert_eq!(
+            Sfloat::decode(&[1]),
+            Err(CodecError::InvalidLength {
+                expected: 2,
+                actual: 1
+            })
+        );
+        assert_eq!(
+            Float::decode(&[1, 2, 3, 4, 5]),
+            Err(CodecError::InvalidLength {
+                expected: 4,
+                actual: 5
+            })
+        );
+        assert_eq!(
+            u16::decode(&[]),
+            Err(CodecError::InvalidLength {
+                expected: 2,
+                actual: 0
+            })
+        );
+    }
+
+    #[test]
+    fn round_trips_numbers() {
+        assert_eq!((-2i16).encode(), [0xFE, 0xFF]);
+        assert_eq!(i16::decode(&(-2i16).encode()), Ok(-2));
+        assert_eq!(i64::decode(&i64::MIN.encode()), Ok(i64::MIN));
+        assert_eq!(1.5f32.encode(), 1.5f32.to_le_bytes());
+        assert_eq!(f32::decode(&(-36.4f32).encode()), Ok(-36.4));
+    }
+
+    #[test]
+    fn encodes_big_endian() {
+        assert_eq!(BigEndian(0x1234u16).encode(), [0x12, 0x34]);
+        assert_eq!(0x1234u16.encode(), [0x34, 0x12]);
+        assert_eq!(
+            BigEndian::<u32>::decode(&[0x01, 0x02, 0x03, 0x04]),
+            Ok(BigEndian(0x0102_0304))
+        );
+        assert_eq!(BigEndian(-2i16).encode(), [0xFF, 0xFE]);
+    }
+
+    #[test]
+    fn rejects_invalid_values() {
+        assert_eq!(bool::decode(&[1]), Ok(true));
+        assert_eq!(
+            bool::decode(&[2]),
+            Err(CodecError::InvalidValue("2 is not a boolean".into()))
+        );
+        assert_eq!(String::decode(b"ok"), Ok("ok".to_string()));
+        assert!(matches!(
+            String::decode(&[0x61, 0xFF]),
+            Err(CodecError::InvalidUtf8(_))
+        ));
+    }
+}
@@ -1,9 +1,11 @@
-use tokio::sync::mpsc::error::SendError;
-
+use crate::CodecError;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
 use thiserror::Error;
 use uuid::Uuid;
 
 #[derive(Debug, Error)]
+#[cfg_attr(feature = "uniffi", derive(uniffi::Error), uniffi(flat_error))]
 pub enum BleError {
     #[error("Btleplug error: {0}")]
     Btleplug(#[from] btleplug::Error),
@@ -26,6 +28,9 @@ pub enum BleError {
     #[error("Java vm not initialized")]
     JavaVM,
 
+    #[error("JNI setup failed: {0}")]
+    JniSetup(String),
+
     #[error("There is no peripheral with id: {0}")]
     UnknownPeripheral(String),
 
@@ -44,21 +49,84 @@ pub enum BleError {
     #[error("Device is already connected.")]
     AlreadyConnected,
 
+    #[error(
+        "blocking calls are not allowed inside an async context, use the async functions instead"
+    )]
+    BlockingInAsyncContext,
+
     #[error("Handler not initialized")]
     HandlerNotInitialized,
 
     #[error("Handler already initialized")]
     HandlerAlreadyInitialized,
 
-    #[error("received wrong data")]
-    WrongData,
+    #[error("received wrong data from {charac}: {source}")]
+    WrongData { charac: Uuid, source: CodecError },
 
-    #[error("could not send devices: {0}")]
-    SendingDevices(SendError<Vec<crate::BleDevice>>),
+    #[error("characteristic {charac} does not support {property}")]
+    MissingProperty {
+        charac: Uuid,
+        property: &'static str,
+    },
+
+    #[error("could not send devices, the receiver was dropped")]
+    SendingDevices,
 
     #[error("could not join fuure: {0}")]
     JoinError(tokio::task::JoinError),
 
+    #[error("no device matching the filter found")]
+    NoMatchingDevice,
+
     #[error("no bluetooth adapters found")]
     NoAdapters,
+
+    #[error("bluetooth adapter is off or unavailable")]
+    AdapterOff,
+
+    #[error("no adapter matching {0} found")]
+    AdapterNotFound(String),
+
+    #[error("invalid address: {0}")]
+    InvalidAddress(#[from] ParseBleAddressError),
+
+    #[error("invalid uuid: {0}")]
+    InvalidUuid(#[from] uuid::Error),
+
+    #[error("the receiving side of the sink was closed")]
+    SinkClosed,
+
+    #[error("invalid recording: {0}")]
+    InvalidRecording(String),
+
+    #[error("replay diverged from the recording: {0}")]
+    ReplayDiverged(String),
+
+    /// recorded error without a matching variant, only its message was recorded
+    #[error("{0}")]
+    Replayed(String),
+
+    #[error("could not write trace: {0}")]
+    Trace(std::io::Error),
+}
+
+/// serialized as the error message, the wrapped platform errors can't be serialized
+#[cfg(feature = "serde")]
+impl Serialize for BleError {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Error)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum ParseBleAddressError {
+    #[error("invalid length {0}, expected 12 hex digits with optional delimiters")]
+    InvalidLength(usize),
+
+    #[error("invalid character {character:?} at position {position}")]
+    InvalidCharacter { character: char, position: usize },
+
+    #[error("invalid delimiter {delimiter:?} at position {position}")]
+    InvalidDelimiter { delimiter: char, position: usize },
 }
@@ -0,0 +1,119 @@
+use crate::{AdapterState, BleAddress, BleDevice, BleDeviceId};
+use btleplug::api::{Central, CentralEvent, Peripheral as _};
+use btleplug::platform::{Adapter, PeripheralId};
+use futures::Stream;
+#[cfg(not(feature = "tracing"))]
+use log::warn;
+use once_cell::sync::Lazy;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use tokio::sync::broadcast::{self, error::RecvError};
+#[cfg(feature = "tracing")]
+use tracing::warn;
+use uuid::Uuid;
+
+/// number of events buffered per subscriber before old events are dropped
+const EVENT_BUFFER: usize = 64;
+
+static EVENTS: Lazy<broadcast::Sender<BleEvent>> = Lazy::new(|| broadcast::channel(EVENT_BUFFER).0);
+
+/// Events reported by the adapter, translated to blec types
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum BleEvent {
+    /// a device was seen for the first time, devices without a name have an empty name
+    DeviceDiscovered(BleDevice),
+    /// properties of a device like the rssi changed
+    DeviceUpdated(BleDevice),
+    DeviceConnected(BleDevice),
+    DeviceDisconnected(BleDevice),
+    ManufacturerData {
+        id: BleDeviceId,
+        address: BleAddress,
+        /// data by company id
+        data: HashMap<u16, Vec<u8>>,
+    },
+    ServiceData {
+        id: BleDeviceId,
+        address: BleAddress,
+        /// data by service uuid
+        data: HashMap<Uuid, Vec<u8>>,
+    },
+    ServicesAdvertised {
+        id: BleDeviceId,
+        address: BleAddress,
+        services: Vec<Uuid>,
+    },
+    AdapterStateChanged(AdapterState),
+    /// handling an event failed or the event stream could not be acquired,
+    /// the event loop keeps running or is restarted
+    EventLoopError(String),
+}
+
+/// Stream of all events emitted after the call.
+/// If the stream is not polled fast enough old events are dropped.
+pub(crate) fn subscribe() -> impl Stream<Item = BleEvent> {
+    futures::stream::unfold(EVENTS.subscribe(), |mut rx| async move {
+        loop {
+            match rx.recv().await {
+                Ok(event) => return Some((event, rx)),
+                Err(RecvError::Lagged(n)) => warn!("event stream lagging, dropped {n} events"),
+                Err(RecvError::Closed) => return None,
+            }
+        }
+    })
+}
+
+pub(crate) fn has_subscribers() -> bool {
+    EVENTS.receiver_count() > 0
+}
+
+pub(crate) fn emit(event: BleEvent) {
+    // fails only without subscribers
+    let _ = EVENTS.send(event);
+}
+
+/// Translates an adapter event, returns `None` if the peripheral is no longer known to the adapter
+pub(crate) async fn translate(adapter: &Adapter, event: &CentralEvent) -> Option<BleEvent> {
+    let event = match event {
+        CentralEvent::DeviceDiscovered(id) => {
+            BleEvent::DeviceDiscovered(device(adapter, id).await?)
+        }
+        CentralEvent::DeviceUpdated(id) => BleEvent::DeviceUpdated(device(adapter, id).await?),
+        CentralEvent::DeviceConnected(id) => BleEvent::DeviceConnected(device(adapter, id).await?),
+        CentralEvent::DeviceDisconnected(id) => {
+            BleEvent::DeviceDisconnected(device(adapter, id).await?)
+        }
+        CentralEvent::ManufacturerDataAdvertisement {
+            id,
+            manufacturer_data,
+        } => BleEvent::ManufacturerData {
+            id: id.clone().into(),
+            address: address(adapter, id).await?,
+            data: manufacturer_data.clone(),
+        },
+        CentralEvent::ServiceDataAdvertisement { id, service_data } => BleEvent::ServiceData {
+            id: id.clone().into(),
+            address: address(adapter, id).await?,
+            data: service_data.clone(),
+        },
+        CentralEvent::ServicesAdvertisement { id, services } => BleEvent::ServicesAdvertised {
+            id: id.clone().into(),
+            address: address(adapter, id).await?,
+            services: services.clone(),
+        },
+        CentralEvent::StateUpdate(state) => BleEvent::AdapterStateChanged(state.clone().into()),
+    };
+    Some(event)
+}
+
+async fn device(adapter: &Adapter, id: &PeripheralId) -> Option<BleDevice> {
+    let peripheral = adapter.peripheral(id).await.ok()?;
+    BleDevice::from_peripheral_unnamed(&peripheral).await.ok()
+}
+
+async fn address(adapter: &Adapter, id: &PeripheralId) -> Option<BleAddress> {
+    let peripheral = adapter.peripheral(id).await.ok()?;
+    Some(peripheral.address().into())
+}
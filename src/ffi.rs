@@ -0,0 +1,478 @@
+//! C ABI for using blec from C, C++, Swift or Dart FFI.
+//!
+//! All functions block until the operation finished and return a [BlecResult].
+//! If a call fails [blec_last_error_message] returns a description of the error.
+//! The header `include/blec.h` is generated with `cbindgen --config cbindgen.toml --output include/blec.h`.
+
+use crate::{blocking, BleAddress, BleDevice, BleError, ConnectTarget, DisconnectReason};
+use std::any::Any;
+use std::cell::RefCell;
+use std::ffi::{c_char, c_void, CStr, CString};
+use std::panic::{self, AssertUnwindSafe};
+use std::ptr;
+use std::slice;
+use uuid::Uuid;
+
+/// Result of all calls, `BLEC_RESULT_OK` on success
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BlecResult {
+    Ok = 0,
+    /// a null pointer, invalid UUID or address was passed
+    InvalidArgument,
+    NotInitialized,
+    NoDeviceConnected,
+    AlreadyConnected,
+    DeviceNotFound,
+    ServiceNotFound,
+    CharacNotAvailable,
+    AdapterOff,
+    NoAdapters,
+    /// the call was made from inside an async context
+    BlockingInAsyncContext,
+    /// blec panicked, see [blec_last_error_message]
+    Panic,
+    /// any other error, see [blec_last_error_message]
+    Other,
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BlecDisconnectReason {
+    UserInitiated,
+    Remote,
+    LinkLoss,
+    AdapterOff,
+}
+
+/// Opaque device, accessed with the `blec_device_*` functions
+pub struct BlecDevice {
+    device: BleDevice,
+    name: CString,
+}
+
+/// Opaque list of devices returned by [blec_discover], free it with [blec_device_list_free]
+pub struct BlecDeviceList {
+    devices: Vec<BlecDevice>,
+}
+
+/// Called with the data of every notification, `data` is only valid during the call. Must not be null.
+pub type BlecNotifyCallback =
+    Option<extern "C" fn(user_data: *mut c_void, data: *const u8, len: usize)>;
+
+/// Called once when the connection ends, `device` is only valid during the call. Can be null.
+pub type BlecDisconnectCallback = Option<
+    extern "C" fn(user_data: *mut c_void, device: *const BlecDevice, reason: BlecDisconnectReason),
+>;
+
+/// User data pointer passed back to callbacks, the caller is responsible for it being usable from other threads
+#[derive(Clone, Copy)]
+struct UserData(*mut c_void);
+unsafe impl Send for UserData {}
+unsafe impl Sync for UserData {}
+
+std::thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+impl From<&BleError> for BlecResult {
+    fn from(e: &BleError) -> Self {
+        match e {
+            BleError::InvalidAddress(_) | BleError::InvalidUuid(_) => Self::InvalidArgument,
+            BleError::RuntimeNotInitialized | BleError::HandlerNotInitialized => {
+                Self::NotInitialized
+            }
+            BleError::NoDeviceConnected => Self::NoDeviceConnected,
+            BleError::AlreadyConnected => Self::AlreadyConnected,
+            BleError::UnknownPeripheral(_) | BleError::NoMatchingDevice => Self::DeviceNotFound,
+            BleError::ServiceNotFound => Self::ServiceNotFound,
+            BleError::CharacNotFound(_) | BleError::CharacNotAvailable(_) => {
+                Self::CharacNotAvailable
+            }
+            BleError::AdapterOff => Self::AdapterOff,
+            BleError::NoAdapters | BleError::AdapterNotFound(_) => Self::NoAdapters,
+            BleError::BlockingInAsyncContext => Self::BlockingInAsyncContext,
+            _ => Self::Other,
+        }
+    }
+}
+
+impl From<DisconnectReason> for BlecDisconnectReason {
+    fn from(reason: DisconnectReason) -> Self {
+        match reason {
+            DisconnectReason::UserInitiated => Self::UserInitiated,
+            DisconnectReason::Remote => Self::Remote,
+            DisconnectReason::LinkLoss => Self::LinkLoss,
+            DisconnectReason::AdapterOff => Self::AdapterOff,
+        }
+    }
+}
+
+impl From<BleDevice> for BlecDevice {
+    fn from(device: BleDevice) -> Self {
+        // local names can't contain nul bytes in practice, an empty name is used otherwise
+        let name = CString::new(device.name.clone()).unwrap_or_default();
+        Self { device, name }
+    }
+}
+
+/// Error of a single call, invalid arguments are reported separately from [BleError]s
+enum CallError {
+    InvalidArgument(String),
+    Ble(BleError),
+}
+
+impl From<BleError> for CallError {
+    fn from(e: BleError) -> Self {
+        Self::Ble(e)
+    }
+}
+
+fn invalid_argument(message: impl Into<String>) -> CallError {
+    CallError::InvalidArgument(message.into())
+}
+
+fn set_last_error(message: String) {
+    // error messages never contain nul bytes, an empty message is used otherwise
+    let message = CString::new(message).unwrap_or_default();
+    LAST_ERROR.with(|e| *e.borrow_mut() = Some(message));
+}
+
+/// Runs the call and stores the error message for [blec_last_error_message].
+/// Panics are caught, unwinding into the caller would abort it.
+fn run(f: impl FnOnce() -> Result<(), CallError>) -> BlecResult {
+    match panic::catch_unwind(AssertUnwindSafe(f)) {
+        Ok(Ok(())) => BlecResult::Ok,
+        Ok(Err(CallError::InvalidArgument(message))) => {
+            set_last_error(message);
+            BlecResult::InvalidArgument
+        }
+        Ok(Err(CallError::Ble(e))) => {
+            set_last_error(e.to_string());
+            BlecResult::from(&e)
+        }
+        Err(payload) => {
+            set_last_error(format!("blec panicked: {}", panic_message(&*payload)));
+            BlecResult::Panic
+        }
+    }
+}
+
+fn panic_message(payload: &(dyn Any + Send)) -> &str {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        message
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message
+    } else {
+        "unknown panic"
+    }
+}
+
+unsafe fn str_arg<'a>(s: *const c_char, name: &str) -> Result<&'a str, CallError> {
+    if s.is_null() {
+        return Err(invalid_argument(format!("{name} is null")));
+    }
+    CStr::from_ptr(s)
+        .to_str()
+        .map_err(|_| invalid_argument(format!("{name} is not valid UTF-8")))
+}
+
+unsafe fn uuid_arg(s: *const c_char, name: &str) -> Result<Uuid, CallError> {
+    let s = str_arg(s, name)?;
+    Uuid::parse_str(s).map_err(|e| invalid_argument(format!("{name} is not a valid UUID: {e}")))
+}
+
+unsafe fn uuid_list_arg(list: *const *const c_char, len: usize) -> Result<Vec<Uuid>, CallError> {
+    if len == 0 {
+        return Ok(vec![]);
+    }
+    if list.is_null() {
+        return Err(invalid_argument("characs is null"));
+    }
+    slice::from_raw_parts(list, len)
+        .iter()
+        .map(|s| uuid_arg(*s, "charac"))
+        .collect()
+}
+
+fn disconnect_callback(
+    callback: BlecDisconnectCallback,
+    user_data: *mut c_void,
+) -> Option<impl FnOnce(BleDevice, DisconnectReason) + Send + 'static> {
+    let user_data = UserData(user_data);
+    callback.map(|callback| {
+        move |device: BleDevice, reason: DisconnectReason| {
+            let user_data = user_data;
+            let device = BlecDevice::from(device);
+            callback(user_data.0, &device, reason.into());
+        }
+    })
+}
+
+/// Initializes blec, has to be called before anything else. Calling it again does nothing.
+#[no_mangle]
+pub extern "C" fn blec_init() -> BlecResult {
+    run(|| Ok(crate::init()?))
+}
+
+/// Disconnects and stops blec, [blec_init] can be called again afterwards
+#[no_mangle]
+pub extern "C" fn blec_deinit() -> BlecResult {
+    run(|| Ok(crate::deinit()?))
+}
+
+/// Returns the message of the last error on this thread or null.
+/// The string is valid until the next failing blec call on this thread.
+#[no_mangle]
+pub extern "C" fn blec_last_error_message() -> *const c_char {
+    LAST_ERROR.with(|e| e.borrow().as_ref().map_or(ptr::null(), |e| e.as_ptr()))
+}
+
+/// Scans for `timeout_ms` milliseconds and stores the discovered devices in `out`.
+///
+/// # Safety
+/// `out` must be a valid pointer, the list stored in it must be freed with [blec_device_list_free].
+#[no_mangle]
+pub unsafe extern "C" fn blec_discover(
+    timeout_ms: u64,
+    out: *mut *mut BlecDeviceList,
+) -> BlecResult {
+    run(|| {
+        if out.is_null() {
+            return Err(invalid_argument("out is null"));
+        }
+        let devices = blocking::discover(timeout_ms)?;
+        let list = BlecDeviceList {
+            devices: devices.into_iter().map(Into::into).collect(),
+        };
+        *out = Box::into_raw(Box::new(list));
+        Ok(())
+    })
+}
+
+/// # Safety
+/// `list` must be null or returned by [blec_discover]
+#[no_mangle]
+pub unsafe extern "C" fn blec_device_list_len(list: *const BlecDeviceList) -> usize {
+    list.as_ref().map_or(0, |l| l.devices.len())
+}
+
+/// Returns the device at `index` or null, it is valid until the list is freed.
+///
+/// # Safety
+/// `list` must be null or returned by [blec_discover]
+#[no_mangle]
+pub unsafe extern "C" fn blec_device_list_get(
+    list: *const BlecDeviceList,
+    index: usize,
+) -> *const BlecDevice {
+    list.as_ref()
+        .and_then(|l| l.devices.get(index))
+        .map_or(ptr::null(), |d| d as *const BlecDevice)
+}
+
+/// # Safety
+/// `list` must be null or returned by [blec_discover] and not be used afterwards
+#[no_mangle]
+pub unsafe extern "C" fn blec_device_list_free(list: *mut BlecDeviceList) {
+    if !list.is_null() {
+        drop(Box::from_raw(list));
+    }
+}
+
+/// Returns the local name of the device, valid as long as the device
+///
+/// # Safety
+/// `device` must be a valid device pointer
+#[no_mangle]
+pub unsafe extern "C" fn blec_device_name(device: *const BlecDevice) -> *const c_char {
+    device.as_ref().map_or(ptr::null(), |d| d.name.as_ptr())
+}
+
+/// Writes the 6 address bytes, most significant first, to `out`
+///
+/// # Safety
+/// `device` must be a valid device pointer and `out` must point to 6 writable bytes
+#[no_mangle]
+pub unsafe extern "C" fn blec_device_address(
+    device: *const BlecDevice,
+    out: *mut u8,
+) -> BlecResult {
+    run(|| {
+        let device = device
+            .as_ref()
+            .ok_or_else(|| invalid_argument("device is null"))?;
+        if out.is_null() {
+            return Err(invalid_argument("out is null"));
+        }
+        ptr::copy_nonoverlapping(device.device.address.address.as_ptr(), out, 6);
+        Ok(())
+    })
+}
+
+/// Returns the rssi of the device or `INT16_MIN` if unknown
+///
+/// # Safety
+/// `device` must be a valid device pointer
+#[no_mangle]
+pub unsafe extern "C" fn blec_device_rssi(device: *const BlecDevice) -> i16 {
+    device
+        .as_ref()
+        .and_then(|d| d.device.rssi)
+        .unwrap_or(i16::MIN)
+}
+
+/// Connects to the device with the given address (`AA:BB:CC:DD:EE:FF`).
+/// `on_disconnect` is optional and called once when the connection ends.
+///
+/// # Safety
+/// All strings must be valid nul terminated strings, `characs` must point to `characs_len` strings.
+/// `user_data` is passed to the callback from another thread.
+#[no_mangle]
+pub unsafe extern "C" fn blec_connect(
+    address: *const c_char,
+    service: *const c_char,
+    characs: *const *const c_char,
+    characs_len: usize,
+    on_disconnect: BlecDisconnectCallback,
+    user_data: *mut c_void,
+) -> BlecResult {
+    run(|| {
+        let address: BleAddress = str_arg(address, "address")?
+            .parse()
+            .map_err(|e| invalid_argument(format!("address is invalid: {e}")))?;
+        connect(
+            address.into(),
+            service,
+            characs,
+            characs_len,
+            on_disconnect,
+            user_data,
+        )
+    })
+}
+
+/// Connects to a device returned by [blec_discover] using its platform-stable id.
+///
+/// # Safety
+/// Same as [blec_connect], `device` must be a valid device pointer.
+#[no_mangle]
+pub unsafe extern "C" fn blec_connect_device(
+    device: *const BlecDevice,
+    service: *const c_char,
+    characs: *const *const c_char,
+    characs_len: usize,
+    on_disconnect: BlecDisconnectCallback,
+    user_data: *mut c_void,
+) -> BlecResult {
+    run(|| {
+        let device = device
+            .as_ref()
+            .ok_or_else(|| invalid_argument("device is null"))?;
+        connect(
+            device.device.id.clone().into(),
+            service,
+            characs,
+            characs_len,
+            on_disconnect,
+            user_data,
+        )
+    })
+}
+
+unsafe fn connect(
+    target: ConnectTarget,
+    service: *const c_char,
+    characs: *const *const c_char,
+    characs_len: usize,
+    on_disconnect: BlecDisconnectCallback,
+    user_data: *mut c_void,
+) -> Result<(), CallError> {
+    let service = uuid_arg(service, "service")?;
+    let characs = uuid_list_arg(characs, characs_len)?;
+    let on_disconnect = disconnect_callback(on_disconnect, user_data);
+    Ok(blocking::connect(target, service, characs, on_disconnect)?)
+}
+
+#[no_mangle]
+pub extern "C" fn blec_disconnect() -> BlecResult {
+    run(|| Ok(blocking::disconnect()?))
+}
+
+/// Writes `len` bytes to the characteristic.
+///
+/// # Safety
+/// `charac` must be a valid nul terminated string and `data` must point to `len` bytes
+#[no_mangle]
+pub unsafe extern "C" fn blec_send(
+    charac: *const c_char,
+    data: *const u8,
+    len: usize,
+) -> BlecResult {
+    run(|| {
+        let charac = uuid_arg(charac, "charac")?;
+        if data.is_null() && len > 0 {
+            return Err(invalid_argument("data is null"));
+        }
+        let data = if len == 0 {
+            vec![]
+        } else {
+            slice::from_raw_parts(data, len).to_vec()
+        };
+        Ok(blocking::send_data(charac, data)?)
+    })
+}
+
+/// Reads the characteristic, the data stored in `out_data`/`out_len` must be freed with [blec_buffer_free].
+///
+/// # Safety
+/// `charac` must be a valid nul terminated string, `out_data` and `out_len` must be valid pointers
+#[no_mangle]
+pub unsafe extern "C" fn blec_recv(
+    charac: *const c_char,
+    out_data: *mut *mut u8,
+    out_len: *mut usize,
+) -> BlecResult {
+    run(|| {
+        let charac = uuid_arg(charac, "charac")?;
+        if out_data.is_null() || out_len.is_null() {
+            return Err(invalid_argument("out_data or out_len is null"));
+        }
+        let data = blocking::recv_data(charac)?.into_boxed_slice();
+        *out_len = data.len();
+        *out_data = Box::into_raw(data) as *mut u8;
+        Ok(())
+    })
+}
+
+/// # Safety
+/// `data` and `len` must be returned by [blec_recv] and `data` must not be used afterwards
+#[no_mangle]
+pub unsafe extern "C" fn blec_buffer_free(data: *mut u8, len: usize) {
+    if !data.is_null() {
+        drop(Box::from_raw(ptr::slice_from_raw_parts_mut(data, len)));
+    }
+}
+
+/// Calls `callback` with every notification of the characteristic, `callback` must not be null.
+///
+/// # Safety
+/// `charac` must be a valid nul terminated string.
+/// `user_data` is passed to the callback from another thread.
+#[no_mangle]
+pub unsafe extern "C" fn blec_subscribe(
+    charac: *const c_char,
+    callback: BlecNotifyCallback,
+    user_data: *mut c_void,
+) -> BlecResult {
+    run(|| {
+        let charac = uuid_arg(charac, "charac")?;
+        let callback = callback.ok_or_else(|| invalid_argument("callback is null"))?;
+        let user_data = UserData(user_data);
+        blocking::subscribe(charac, move |data: &[u8]| {
+            let user_data = user_data;
+            callback(user_data.0, data.as_ptr(), data.len());
+        })?;
+        Ok(())
+    })
+}
@@ -0,0 +1,264 @@
+//! API for [flutter_rust_bridge](https://github.com/fzyzcjy/flutter_rust_bridge) and similar binding generators.
+//!
+//! Uses only plain data types: uuids, addresses and ids are passed as strings.
+//! Outputs that produce multiple values are sent to a [Sink], implement it for the generated
+//! `StreamSink` to stream them to Dart:
+//!
+//! ```ignore
+//! impl<T: SseEncode + Send + Sync + 'static> blec::frb::Sink<T> for StreamSink<T> {
+//!     fn add(&self, item: T) -> Result<(), BleError> {
+//!         StreamSink::add(self, item).map_err(|_| BleError::SinkClosed)
+//!     }
+//!     fn add_error(&self, error: BleError) -> Result<(), BleError> {
+//!         StreamSink::add_error(self, error.to_string()).map_err(|_| BleError::SinkClosed)
+//!     }
+//! }
+//! ```
+
+use crate::{ble, AdapterState, BleDevice, BleError, DisconnectReason};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use tokio::sync::mpsc;
+use uuid::Uuid;
+
+/// Receives items produced by blec, e.g. a `StreamSink` of flutter_rust_bridge
+pub trait Sink<T>: Send + Sync + 'static {
+    /// Fails with [BleError::SinkClosed] if nobody is listening anymore
+    fn add(&self, item: T) -> Result<(), BleError>;
+    fn add_error(&self, error: BleError) -> Result<(), BleError>;
+}
+
+impl<T: Send + 'static> Sink<T> for mpsc::UnboundedSender<Result<T, BleError>> {
+    fn add(&self, item: T) -> Result<(), BleError> {
+        self.send(Ok(item)).map_err(|_| BleError::SinkClosed)
+    }
+
+    fn add_error(&self, error: BleError) -> Result<(), BleError> {
+        self.send(Err(error)).map_err(|_| BleError::SinkClosed)
+    }
+}
+
+impl<T: Send + 'static> Sink<T> for std::sync::mpsc::Sender<Result<T, BleError>> {
+    fn add(&self, item: T) -> Result<(), BleError> {
+        self.send(Ok(item)).map_err(|_| BleError::SinkClosed)
+    }
+
+    fn add_error(&self, error: BleError) -> Result<(), BleError> {
+        self.send(Err(error)).map_err(|_| BleError::SinkClosed)
+    }
+}
+
+/// [BleDevice] with all fields converted to plain types
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Device {
+    /// string representation of the [BleDeviceId](crate::BleDeviceId), can be passed to [connect]
+    pub id: String,
+    pub address: String,
+    pub name: String,
+    pub is_connected: bool,
+    pub rssi: Option<i16>,
+    pub services: Vec<String>,
+    pub manufacturer_data: HashMap<u16, Vec<u8>>,
+    pub service_data: HashMap<String, Vec<u8>>,
+}
+
+impl From<BleDevice> for Device {
+    fn from(device: BleDevice) -> Self {
+        Self {
+            id: device.id.to_string(),
+            address: device.address.to_string(),
+            name: device.name,
+            is_connected: device.is_connected,
+            rssi: device.rssi,
+            services: device.services.iter().map(Uuid::to_string).collect(),
+            manufacturer_data: device.manufacturer_data,
+            service_data: device
+                .service_data
+                .into_iter()
+                .map(|(uuid, data)| (uuid.to_string(), data))
+                .collect(),
+        }
+    }
+}
+
+/// Changes of the connection established with [connect]
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum ConnectionEvent {
+    Connected(Device),
+    Disconnected {
+        device: Device,
+        reason: DisconnectReason,
+    },
+}
+
+/// Scans for [timeout_ms] milliseconds and periodically sends the discovered devices to the sink
+pub fn discover(sink: impl Sink<Vec<Device>>, timeout_ms: u64) -> Result<(), BleError> {
+    let (tx, mut rx) = mpsc::channel(1);
+    ble::discover(tx, timeout_ms)?;
+    crate::spawn(async move {
+        while let Some(result) = rx.recv().await {
+            let sent = match result {
+                Ok(devices) => sink.add(devices.into_iter().map(Into::into).collect()),
+                Err(e) => sink.add_error(e),
+            };
+            if sent.is_err() {
+                break;
+            }
+        }
+    })
+}
+
+/// Connects to the device with the given address or device id, unknown ids are scanned for.
+/// [ConnectionEvent::Connected] is sent to the sink on success and [ConnectionEvent::Disconnected]
+/// once the connection ends.
+pub async fn connect(
+    target: String,
+    service: String,
+    characs: Vec<String>,
+    events: impl Sink<ConnectionEvent>,
+) -> Result<Device, BleError> {
+    let target = ble::parse_target(target).await?;
+    let service = Uuid::parse_str(&service)?;
+    let characs = ble::parse_uuids(&characs)?;
+    let events = Arc::new(events);
+    // a disconnect right after connecting is held back until Connected was sent
+    let pending = Arc::new(Mutex::new(Some(None)));
+    let on_disconnect = {
+        let (events, pending) = (events.clone(), pending.clone());
+        move |device: BleDevice, reason| {
+            let event = ConnectionEvent::Disconnected {
+                device: device.into(),
+                reason,
+            };
+            match pending.lock().unwrap_or_else(|e| e.into_inner()).as_mut() {
+                Some(held) => *held = Some(event),
+                None => {
+                    let _ = events.add(event);
+                }
+            }
+        }
+    };
+    ble::connect(target, service, characs, Some(on_disconnect)).await?;
+    let connected = ble::connected_device().await;
+    let mut pending = pending.lock().unwrap_or_else(|e| e.into_inner());
+    let disconnected = pending.take().flatten();
+    let device = match (connected, &disconnected) {
+        (Ok(device), _) => Device::from(device),
+        (Err(_), Some(ConnectionEvent::Disconnected { device, .. })) => device.clone(),
+        (Err(e), _) => return Err(e),
+    };
+    // the connection is established even if nobody listens for the events
+    let _ = events.add(ConnectionEvent::Connected(device.clone()));
+    if let Some(event) = disconnected {
+        let _ = events.add(event);
+    }
+    Ok(device)
+}
+
+pub async fn disconnect() -> Result<(), BleError> {
+    ble::disconnect().await
+}
+
+pub async fn send_data(charac: String, data: Vec<u8>) -> Result<(), BleError> {
+    ble::send_data(Uuid::parse_str(&charac)?, data).await
+}
+
+pub async fn recv_data(charac: String) -> Result<Vec<u8>, BleError> {
+    ble::recv_data(Uuid::parse_str(&charac)?).await
+}
+
+/// Sends every notification of the characteristic to the sink
+pub async fn subscribe(charac: String, sink: impl Sink<Vec<u8>>) -> Result<(), BleError> {
+    ble::subscribe(Uuid::parse_str(&charac)?, move |data: &[u8]| {
+        let _ = sink.add(data.to_vec());
+    })
+    .await
+}
+
+pub async fn is_connected() -> Result<bool, BleError> {
+    ble::is_connected().await
+}
+
+pub async fn connected_device() -> Result<Device, BleError> {
+    Ok(ble::connected_device().await?.into())
+}
+
+/// Sends the current adapter state and every change to the sink
+pub fn adapter_state(sink: impl Sink<AdapterState>) -> Result<(), BleError> {
+    sink.add(ble::adapter_state())?;
+    ble::on_adapter_state(move |state| {
+        let _ = sink.add(state);
+    })
+}
+
+#[cfg(all(test, feature = "record"))]
+mod tests {
+    use super::*;
+    use crate::record::{self, RecordEntry, RecordedDevice, RecordedOp, RecordedTarget, Recording};
+    use crate::BleAddress;
+
+    #[test]
+    fn sends_connected_before_an_early_disconnect() {
+        let _serial = record::tests::SERIAL
+            .lock()
+            .unwrap_or_else(|e| e.into_inner());
+        let address = BleAddress {
+            address: [1, 2, 3, 4, 5, 6],
+        };
+        let device = RecordedDevice {
+            id: address.to_string(),
+            address,
+            address_type: None,
+            name: "Battery".to_string(),
+            is_connected: true,
+            rssi: None,
+            services: vec![],
+            manufacturer_data: Default::default(),
+            service_data: Default::default(),
+        };
+        let ops = [
+            RecordedOp::Connect {
+                target: Some(RecordedTarget::Address(address)),
+                result: Ok(()),
+                device: Some(device),
+            },
+            RecordedOp::Disconnected {
+                reason: DisconnectReason::Remote,
+            },
+        ];
+        let recording = Recording {
+            version: record::RECORDING_VERSION,
+            entries: ops
+                .into_iter()
+                .map(|op| RecordEntry { at: 0, op })
+                .collect(),
+        };
+        record::start_replay(recording, record::tests::no_timing()).unwrap();
+        let (tx, rx) = std::sync::mpsc::channel();
+        let device = crate::block_on(connect(
+            address.to_string(),
+            Uuid::from_u128(0x180f).to_string(),
+            vec![],
+            tx,
+        ))
+        .unwrap()
+        .unwrap();
+        let timeout = std::time::Duration::from_secs(5);
+        assert_eq!(
+            rx.recv_timeout(timeout).unwrap().unwrap(),
+            ConnectionEvent::Connected(device)
+        );
+        assert!(matches!(
+            rx.recv_timeout(timeout).unwrap(),
+            Ok(ConnectionEvent::Disconnected {
+                reason: DisconnectReason::Remote,
+                ..
+            })
+        ));
+        record::stop_replay().unwrap();
+    }
+}
@@ -0,0 +1,100 @@
+use btleplug::api::{CharPropFlags, Characteristic, Service};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use std::fmt::{self, Display, Formatter};
+use uuid::Uuid;
+
+/// Service of the connected device, returned by [services](crate::services)
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct BleService {
+    pub uuid: Uuid,
+    pub primary: bool,
+    pub characteristics: Vec<BleCharacteristic>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct BleCharacteristic {
+    pub uuid: Uuid,
+    /// uuid of the service the characteristic belongs to
+    pub service: Uuid,
+    pub properties: CharacProperties,
+    /// uuids of the descriptors of the characteristic
+    pub descriptors: Vec<Uuid>,
+}
+
+/// Operations supported by a characteristic
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct CharacProperties {
+    pub broadcast: bool,
+    pub read: bool,
+    pub write_without_response: bool,
+    pub write: bool,
+    pub notify: bool,
+    pub indicate: bool,
+    pub authenticated_signed_writes: bool,
+    pub extended_properties: bool,
+}
+
+impl From<&Service> for BleService {
+    fn from(service: &Service) -> Self {
+        Self {
+            uuid: service.uuid,
+            primary: service.primary,
+            characteristics: service.characteristics.iter().map(Into::into).collect(),
+        }
+    }
+}
+
+impl From<&Characteristic> for BleCharacteristic {
+    fn from(charac: &Characteristic) -> Self {
+        Self {
+            uuid: charac.uuid,
+            service: charac.service_uuid,
+            properties: charac.properties.into(),
+            descriptors: charac.descriptors.iter().map(|d| d.uuid).collect(),
+        }
+    }
+}
+
+impl From<CharPropFlags> for CharacProperties {
+    fn from(flags: CharPropFlags) -> Self {
+        Self {
+            broadcast: flags.contains(CharPropFlags::BROADCAST),
+            read: flags.contains(CharPropFlags::READ),
+            write_without_response: flags.contains(CharPropFlags::WRITE_WITHOUT_RESPONSE),
+            write: flags.contains(CharPropFlags::WRITE),
+            notify: flags.contains(CharPropFlags::NOTIFY),
+            indicate: flags.contains(CharPropFlags::INDICATE),
+            authenticated_signed_writes: flags.contains(CharPropFlags::AUTHENTICATED_SIGNED_WRITES),
+            extended_properties: flags.contains(CharPropFlags::EXTENDED_PROPERTIES),
+        }
+    }
+}
+
+/// Lists the set properties separated by commas, e.g. `read, notify`
+impl Display for CharacProperties {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        let names = [
+            (self.broadcast, "broadcast"),
+            (self.read, "read"),
+            (self.write_without_response, "write-without-response"),
+            (self.write, "write"),
+            (self.notify, "notify"),
+            (self.indicate, "indicate"),
+            (
+                self.authenticated_signed_writes,
+                "authenticated-signed-writes",
+            ),
+            (self.extended_properties, "extended-properties"),
+        ];
+        let set: Vec<_> = names
+            .iter()
+            .filter(|(set, _)| *set)
+            .map(|(_, name)| *name)
+            .collect();
+        f.write_str(&set.join(", "))
+    }
+}
@@ -1,69 +1,263 @@
+use crate::adapter::{self, AdapterInfo, AdapterSelector, AdapterState};
 use crate::setup::RUNTIME;
-use crate::{BleAddress, BleDevice, BleError};
-use btleplug::api::CentralEvent;
-use btleplug::api::{
-    Central, Characteristic, Manager as _, Peripheral as _, ScanFilter, WriteType,
+use crate::{
+    BleDevice, BleDeviceId, BleError, BleService, ConnectStrategy, ConnectTarget, DeviceFilter,
+    DisconnectReason,
 };
+use btleplug::api::CentralEvent;
+use btleplug::api::{Central, Characteristic, Peripheral as _, ScanFilter, WriteType};
 use btleplug::platform::{Adapter, Manager, Peripheral};
 use futures::{Stream, StreamExt};
-use log::debug;
+#[cfg(not(feature = "tracing"))]
+use log::{debug, trace};
 use std::collections::HashMap;
 use std::pin::Pin;
 use std::sync::Arc;
 use std::time::Duration;
 use tokio::sync::{mpsc, Mutex};
 use tokio::task::AbortHandle;
-use tokio::time::sleep;
+use tokio::time::{sleep, Instant};
+#[cfg(feature = "tracing")]
+use tracing::{debug, field::Empty, trace};
 use uuid::Uuid;
 
+/// interval in which the adapter is polled for new peripherals while scanning
+const SCAN_INTERVAL: Duration = Duration::from_millis(200);
+
+type NotifyCallback = Arc<dyn Fn(&[u8]) + Send + Sync>;
+type DisconnectCallback = Mutex<Box<dyn FnOnce(BleDevice, DisconnectReason) + Send>>;
+
+/// Disconnect callback of the current connection.
+/// It is taken when called, so it runs exactly once and never for a later connection.
+struct DisconnectNotifier {
+    /// used if the device can't be read anymore when disconnecting
+    device: BleDevice,
+    callback: DisconnectCallback,
+}
+
 struct Listener {
     uuid: Uuid,
-    callback: Arc<dyn Fn(&[u8]) + Send + Sync>,
+    callback: NotifyCallback,
 }
 
 pub struct BleHandler {
     connected: Option<Arc<Peripheral>>,
     characs: Vec<Characteristic>,
-    devices: HashMap<BleAddress, Peripheral>,
+    devices: HashMap<BleDeviceId, Peripheral>,
+    manager: Manager,
     adapter: Adapter,
+    adapter_info: AdapterInfo,
     notify_abort: Option<AbortHandle>,
     notify_listeners: Arc<Mutex<Vec<Listener>>>,
-    on_disconnect: Option<Mutex<Box<dyn Fn() + Send>>>,
+    on_disconnect: Option<DisconnectNotifier>,
 }
 
 impl BleHandler {
-    pub async fn new() -> Result<Self, BleError> {
+    pub async fn new(selector: &AdapterSelector) -> Result<Self, BleError> {
         let manager = Manager::new().await?;
-        let adapters = manager.adapters().await?;
-        let central = adapters.into_iter().next().ok_or(BleError::NoAdapters)?;
+        let (adapter, adapter_info) = adapter::select_adapter(&manager, selector).await?;
+        debug!("using adapter {}", adapter_info.info);
+        adapter::set_state(adapter::query_state(&adapter).await);
         Ok(Self {
             devices: HashMap::new(),
             characs: vec![],
             connected: None,
-            adapter: central,
+            manager,
+            adapter,
+            adapter_info,
             notify_abort: None,
             notify_listeners: Arc::new(Mutex::new(vec![])),
             on_disconnect: None,
         })
     }
 
+    pub async fn list_adapters(&self) -> Result<Vec<AdapterInfo>, BleError> {
+        let adapters = adapter::list_adapters(&self.manager).await?;
+        Ok(adapters.into_iter().map(|(_, info)| info).collect())
+    }
+
+    pub fn adapter_info(&self) -> &AdapterInfo {
+        &self.adapter_info
+    }
+
+    /// Disconnects and switches to the selected adapter.
+    /// The event stream has to be acquired again afterwards.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all, err))]
+    pub async fn select_adapter(
+        &mut self,
+        selector: &AdapterSelector,
+    ) -> Result<AdapterInfo, BleError> {
+        let (adapter, adapter_info) = adapter::select_adapter(&self.manager, selector).await?;
+        self.disconnect(DisconnectReason::UserInitiated).await?;
+        debug!("switching to adapter {}", adapter_info.info);
+        adapter::set_state(adapter::query_state(&adapter).await);
+        self.adapter = adapter;
+        self.adapter_info = adapter_info.clone();
+        Ok(adapter_info)
+    }
+
+    /// Connects to the target and makes the given characteristics of the service available.
+    /// All characteristics of all services are made available if no service is given.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(skip_all, err, fields(%target, ?service, device = Empty))
+    )]
     pub async fn connect(
         &mut self,
-        address: BleAddress,
+        target: ConnectTarget,
+        service: Option<Uuid>,
+        characs: Vec<Uuid>,
+        on_disconnect: Option<impl FnOnce(BleDevice, DisconnectReason) + Send + 'static>,
+        strategy: ConnectStrategy,
+    ) -> Result<(), BleError> {
+        adapter::ensure_available()?;
+        if let Some(dev) = self.connected.as_ref() {
+            if target.matches(dev) {
+                return Err(BleError::AlreadyConnected);
+            }
+        }
+        let device = self.find_peripheral(target, strategy).await?;
+        self.connect_peripheral(device, service, characs, on_disconnect)
+            .await
+    }
+
+    /// Scans for [timeout] milliseconds and connects to a device matching the filter.
+    /// Connects to the first match unless [strongest] is set,
+    /// in which case the whole timeout is scanned and the match with the highest rssi is used.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(skip_all, err, fields(%service, timeout, strongest, device = Empty))
+    )]
+    pub async fn connect_matching(
+        &mut self,
+        filter: impl DeviceFilter,
         service: Uuid,
         characs: Vec<Uuid>,
-        on_disconnect: Option<impl Fn() + Send + 'static>,
+        timeout: u64,
+        strongest: bool,
+    ) -> Result<BleDevice, BleError> {
+        adapter::ensure_available()?;
+        if let Some(dev) = self.connected.as_ref() {
+            if filter.matches(&BleDevice::from_peripheral(dev).await?) {
+                return Err(BleError::AlreadyConnected);
+            }
+        }
+        self.adapter.start_scan(ScanFilter::default()).await?;
+        let found = self.scan_matching(&filter, timeout, strongest).await;
+        self.stop_scan().await?;
+        let mut matches = found?;
+        if strongest {
+            matches.sort_by_key(|d| std::cmp::Reverse(d.rssi));
+        }
+        let device = matches
+            .into_iter()
+            .next()
+            .ok_or(BleError::NoMatchingDevice)?;
+        let peripheral = self
+            .devices
+            .get(&device.id)
+            .cloned()
+            .ok_or(BleError::UnknownPeripheral(device.id.to_string()))?;
+        self.connect_peripheral(
+            peripheral,
+            Some(service),
+            characs,
+            None::<fn(BleDevice, DisconnectReason)>,
+        )
+        .await?;
+        Ok(device)
+    }
+
+    /// Polls the adapter for [timeout] milliseconds and returns all devices matching the filter.
+    /// Returns as soon as the first match is found unless [all] is set.
+    async fn scan_matching(
+        &mut self,
+        filter: &impl DeviceFilter,
+        timeout: u64,
+        all: bool,
+    ) -> Result<Vec<BleDevice>, BleError> {
+        let deadline = Instant::now() + Duration::from_millis(timeout);
+        let mut matches = vec![];
+        let mut scanning = true;
+        while Instant::now() < deadline {
+            if !self.scan_tick(&mut scanning).await? {
+                continue;
+            }
+            let discovered = self.adapter.peripherals().await?;
+            matches = self.add_devices(discovered).await;
+            matches.retain(|d| filter.matches(d));
+            if !all && !matches.is_empty() {
+                break;
+            }
+        }
+        Ok(matches)
+    }
+
+    async fn connect_peripheral(
+        &mut self,
+        device: Peripheral,
+        service: Option<Uuid>,
+        characs: Vec<Uuid>,
+        on_disconnect: Option<impl FnOnce(BleDevice, DisconnectReason) + Send + 'static>,
     ) -> Result<(), BleError> {
-        if self.devices.len() == 0 {
-            self.discover(None, 1000).await?;
+        // the previous connection ends here, so its disconnect callback is called
+        if self.connected.is_some() {
+            self.disconnect(DisconnectReason::UserInitiated).await?;
         }
-        // connect to the given address
-        self.connect_device(address).await?;
-        // discover service/characteristics
-        self.connect_service(service, &characs).await?;
-        // set callback to run on disconnect
+        // connect to the given device
+        self.connect_device(device).await?;
+        if let Err(e) = self
+            .setup_connection(service, &characs, on_disconnect)
+            .await
+        {
+            // the connect failed, so its callback must never be called
+            self.on_disconnect = None;
+            self.characs.clear();
+            if let Some(dev) = self.connected.take() {
+                if let Err(e) = dev.disconnect().await {
+                    debug!("could not disconnect after the failed connect: {e}");
+                }
+            }
+            return Err(e);
+        }
+        Ok(())
+    }
+
+    /// Sets the callback to run when the current connection ends
+    pub async fn set_on_disconnect(
+        &mut self,
+        on_disconnect: impl FnOnce(BleDevice, DisconnectReason) + Send + 'static,
+    ) -> Result<(), BleError> {
+        let device = BleDevice::from_peripheral_unnamed(self.get_device().await?.as_ref()).await?;
+        self.on_disconnect = Some(DisconnectNotifier {
+            device,
+            callback: Mutex::new(Box::new(on_disconnect)),
+        });
+        Ok(())
+    }
+
+    /// Sets up the connected device, the connection is torn down by the caller if this fails
+    async fn setup_connection(
+        &mut self,
+        service: Option<Uuid>,
+        characs: &[Uuid],
+        on_disconnect: Option<impl FnOnce(BleDevice, DisconnectReason) + Send + 'static>,
+    ) -> Result<(), BleError> {
         if let Some(cb) = on_disconnect {
-            self.on_disconnect = Some(Mutex::new(Box::new(cb)));
+            self.set_on_disconnect(cb).await?;
+        }
+        // discover service/characteristics
+        self.connect_service(service, characs).await?;
+        #[cfg(feature = "snoop")]
+        if crate::snoop::is_tracing() {
+            let dev = self.get_device().await?;
+            // the trace is written without the address type rather than failing the connect
+            let properties = dev.properties().await.ok().flatten().unwrap_or_default();
+            crate::snoop::connected(
+                dev.address().into(),
+                properties.address_type.map(Into::into),
+                &self.characs,
+            );
         }
         // start background task for notifications
         let rt = RUNTIME.get().ok_or(BleError::RuntimeNotInitialized)?;
@@ -75,10 +269,21 @@ impl BleHandler {
         Ok(())
     }
 
-    async fn connect_service(&mut self, service: Uuid, characs: &[Uuid]) -> Result<(), BleError> {
+    async fn connect_service(
+        &mut self,
+        service: Option<Uuid>,
+        characs: &[Uuid],
+    ) -> Result<(), BleError> {
         let device = self.get_device().await?;
         device.discover_services().await?;
         let services = device.services();
+        let Some(service) = service else {
+            self.characs = services
+                .iter()
+                .flat_map(|s| s.characteristics.iter().cloned())
+                .collect();
+            return Ok(());
+        };
         let s = services
             .iter()
             .find(|s| s.uuid == service)
@@ -91,55 +296,200 @@ impl BleHandler {
         Ok(())
     }
 
-    async fn connect_device(&mut self, address: BleAddress) -> Result<(), BleError> {
-        debug!("connecting to {address}",);
-        if let Some(dev) = self.connected.clone() {
-            if address == dev.address() {
-                return Err(BleError::AlreadyConnected.into());
-            }
-        }
-        let device = self
-            .devices
-            .get(&address)
-            .ok_or(BleError::UnknownPeripheral(address.to_string()))?;
+    /// Services and characteristics of the connected device
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(skip_all, err, fields(device = Empty))
+    )]
+    pub async fn services(&mut self) -> Result<Vec<BleService>, BleError> {
+        let device = self.get_device().await?;
+        Ok(device.services().iter().map(Into::into).collect())
+    }
+
+    async fn connect_device(&mut self, device: Peripheral) -> Result<(), BleError> {
+        debug!("connecting to {}", device.address());
+        #[cfg(feature = "tracing")]
+        record_device(&device);
+        #[cfg(feature = "metrics")]
+        let start = std::time::Instant::now();
+        #[cfg(feature = "metrics")]
+        crate::metrics::connecting(&device.id().into(), device.address().into());
         if !device.is_connected().await? {
             debug!("Connecting to device");
             device.connect().await?;
             debug!("Connecting done");
         }
-        self.connected = Some(Arc::new(device.clone()));
+        #[cfg(feature = "metrics")]
+        crate::metrics::connected(device.id().into(), device.address().into(), start.elapsed());
+        self.connected = Some(Arc::new(device));
         Ok(())
     }
 
-    pub async fn disconnect(&mut self) -> Result<(), BleError> {
-        debug!("disconnecting");
+    /// Looks up the id of a known peripheral by its string representation
+    pub async fn device_id(&self, id: &str) -> Result<BleDeviceId, BleError> {
+        if let Some(known) = self.devices.keys().find(|d| d.to_string() == id) {
+            return Ok(known.clone());
+        }
+        self.adapter
+            .peripherals()
+            .await?
+            .into_iter()
+            .map(|p| BleDeviceId::from(p.id()))
+            .find(|d| d.to_string() == id)
+            .ok_or(BleError::UnknownPeripheral(id.to_string()))
+    }
+
+    /// Looks up the id like [device_id](Self::device_id) and scans for up to [timeout] milliseconds if it is unknown,
+    /// e.g. for an id persisted before the app restarted
+    pub async fn scan_device_id(&self, id: &str, timeout: u64) -> Result<BleDeviceId, BleError> {
+        match self.device_id(id).await {
+            Err(BleError::UnknownPeripheral(_)) => {}
+            result => return result,
+        }
+        adapter::ensure_available()?;
+        debug!("scanning for {id}");
+        self.adapter.start_scan(ScanFilter::default()).await?;
+        let found = self.scan_for_id(id, timeout).await;
+        self.stop_scan().await?;
+        found?.ok_or(BleError::UnknownPeripheral(id.to_string()))
+    }
+
+    /// Polls the adapter until a peripheral with the id shows up or [timeout] milliseconds elapsed
+    async fn scan_for_id(&self, id: &str, timeout: u64) -> Result<Option<BleDeviceId>, BleError> {
+        let deadline = Instant::now() + Duration::from_millis(timeout);
+        let mut scanning = true;
+        while Instant::now() < deadline {
+            if !self.scan_tick(&mut scanning).await? {
+                continue;
+            }
+            let peripherals = self.adapter.peripherals().await?;
+            if let Some(found) = peripherals
+                .into_iter()
+                .map(|p| BleDeviceId::from(p.id()))
+                .find(|d| d.to_string() == id)
+            {
+                return Ok(Some(found));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Looks up the peripheral with the given address or id.
+    /// Peripherals the adapter already knows about (e.g. bonded devices) are returned without scanning.
+    async fn find_peripheral(
+        &mut self,
+        target: ConnectTarget,
+        strategy: ConnectStrategy,
+    ) -> Result<Peripheral, BleError> {
+        if let Some(device) = self.devices.values().find(|p| target.matches(p)) {
+            return Ok(device.clone());
+        }
+        if let Some(device) = self.known_peripheral(&target).await? {
+            self.devices.insert(device.id().into(), device.clone());
+            return Ok(device);
+        }
+        let timeout = match strategy {
+            ConnectStrategy::Direct => return Err(BleError::UnknownPeripheral(target.to_string())),
+            ConnectStrategy::ScanUntilFound(timeout) => timeout,
+        };
+        debug!("scanning for {target}");
+        self.adapter.start_scan(ScanFilter::default()).await?;
+        let found = self.scan_for(&target, timeout).await;
+        self.stop_scan().await?;
+        let device = found?.ok_or(BleError::UnknownPeripheral(target.to_string()))?;
+        self.devices.insert(device.id().into(), device.clone());
+        Ok(device)
+    }
+
+    /// Polls the adapter until the target shows up or [timeout] milliseconds elapsed
+    async fn scan_for(
+        &self,
+        target: &ConnectTarget,
+        timeout: u64,
+    ) -> Result<Option<Peripheral>, BleError> {
+        let deadline = Instant::now() + Duration::from_millis(timeout);
+        let mut scanning = true;
+        while Instant::now() < deadline {
+            if !self.scan_tick(&mut scanning).await? {
+                continue;
+            }
+            if let Some(device) = self.known_peripheral(target).await? {
+                return Ok(Some(device));
+            }
+        }
+        Ok(None)
+    }
+
+    async fn known_peripheral(
+        &self,
+        target: &ConnectTarget,
+    ) -> Result<Option<Peripheral>, BleError> {
+        let peripherals = self.adapter.peripherals().await?;
+        Ok(peripherals.into_iter().find(|p| target.matches(p)))
+    }
+
+    #[cfg(feature = "record")]
+    pub(crate) fn has_connection(&self) -> bool {
+        self.connected.is_some()
+    }
+
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(skip_all, err, fields(?reason, device = Empty))
+    )]
+    pub async fn disconnect(&mut self, reason: DisconnectReason) -> Result<(), BleError> {
+        debug!("disconnecting ({reason:?})");
         if let Some(notify) = self.notify_abort.as_ref() {
             notify.abort();
             self.notify_abort = None;
         }
         *self.notify_listeners.lock().await = vec![];
-        if let Some(dev) = self.connected.as_mut() {
+        let mut result = Ok(());
+        if let Some(dev) = self.connected.take() {
+            // user initiated disconnects are recorded by ble::disconnect
+            #[cfg(feature = "record")]
+            if reason != DisconnectReason::UserInitiated {
+                crate::record::record(|| crate::record::RecordedOp::Disconnected { reason });
+            }
+            #[cfg(feature = "snoop")]
+            crate::snoop::disconnected(reason);
+            #[cfg(feature = "tracing")]
+            record_device(&dev);
+            #[cfg(feature = "metrics")]
+            crate::metrics::disconnected(reason);
             if let Ok(true) = dev.is_connected().await {
-                dev.disconnect().await?;
+                result = dev.disconnect().await.map_err(BleError::from);
+            }
+            if let Some(notifier) = self.on_disconnect.take() {
+                let device = match BleDevice::from_peripheral_unnamed(&dev).await {
+                    Ok(device) => device,
+                    Err(_) => BleDevice {
+                        is_connected: false,
+                        ..notifier.device
+                    },
+                };
+                // not called directly so the callback can use blec without deadlocking on the handler
+                let callback = notifier.callback.into_inner();
+                tokio::task::spawn_blocking(move || callback(device, reason));
             }
-            self.connected = None;
-        }
-        if let Some(on_disconnect) = &self.on_disconnect {
-            let callback = on_disconnect.lock().await;
-            callback();
         }
         self.characs.clear();
         self.devices.clear();
-        Ok(())
+        result
     }
 
     /// Scans for [timeout] milliseconds and periodically sends discovered devices
     /// Also returns vector with all devices after timeout
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(skip_all, err, fields(timeout, devices = Empty))
+    )]
     pub async fn discover(
         &mut self,
-        tx: Option<mpsc::Sender<Vec<BleDevice>>>,
+        tx: Option<mpsc::Sender<Result<Vec<BleDevice>, BleError>>>,
         timeout: u64,
     ) -> Result<Vec<BleDevice>, BleError> {
+        adapter::ensure_available()?;
         self.adapter
             .start_scan(ScanFilter {
                 // services: vec![*SERVICE_UUID],
@@ -147,29 +497,58 @@ impl BleHandler {
             })
             .await?;
         self.devices.clear();
-        let loops = (timeout as f64 / 200.0).round() as u64;
+        let loops = (timeout as f64 / SCAN_INTERVAL.as_millis() as f64).round() as u64;
         let mut devices = vec![];
+        let mut scanning = true;
         for _ in 0..loops {
-            sleep(Duration::from_millis(200)).await;
+            if !self.scan_tick(&mut scanning).await? {
+                continue;
+            }
             let discovered = self.adapter.peripherals().await?;
             devices = self.add_devices(discovered).await;
             if !devices.is_empty() {
                 if let Some(tx) = &tx {
-                    tx.send(devices.clone())
+                    tx.send(Ok(devices.clone()))
                         .await
-                        .map_err(|e| BleError::SendingDevices(e))?;
+                        .map_err(|_| BleError::SendingDevices)?;
                 }
             }
         }
-        self.adapter.stop_scan().await?;
+        self.stop_scan().await?;
+        #[cfg(feature = "tracing")]
+        tracing::Span::current().record("devices", devices.len());
         Ok(devices)
     }
 
+    /// Waits one [SCAN_INTERVAL] and pauses or resumes the scan if the adapter went down or came back.
+    /// Returns whether the adapter is scanning.
+    async fn scan_tick(&self, scanning: &mut bool) -> Result<bool, BleError> {
+        sleep(SCAN_INTERVAL).await;
+        let available = adapter::current_state().is_available();
+        if *scanning && !available {
+            debug!("adapter not available, pausing scan");
+            *scanning = false;
+        } else if !*scanning && available {
+            debug!("adapter available again, resuming scan");
+            self.adapter.start_scan(ScanFilter::default()).await?;
+            *scanning = true;
+        }
+        Ok(*scanning)
+    }
+
+    /// Stops scanning unless the scan was paused because the adapter is down
+    async fn stop_scan(&self) -> Result<(), BleError> {
+        if adapter::current_state().is_available() {
+            self.adapter.stop_scan().await?;
+        }
+        Ok(())
+    }
+
     async fn add_devices(&mut self, discovered: Vec<Peripheral>) -> Vec<BleDevice> {
         let mut devices = vec![];
         for p in discovered {
             if let Ok(dev) = BleDevice::from_peripheral(&p).await {
-                self.devices.insert(dev.address.clone(), p);
+                self.devices.insert(dev.id.clone(), p);
                 devices.push(dev);
             }
         }
@@ -177,32 +556,65 @@ impl BleHandler {
         devices
     }
 
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(skip_all, err, fields(charac = %c, bytes = data.len(), device = Empty))
+    )]
     pub async fn send_data(&mut self, c: Uuid, data: &[u8]) -> Result<(), BleError> {
         let dev = self.get_device().await?;
         let charac = self.get_charac(c)?;
-        dev.write(charac, &data, WriteType::WithoutResponse).await?;
+        #[cfg(feature = "metrics")]
+        let start = std::time::Instant::now();
+        let result = dev
+            .write(charac, data, WriteType::WithoutResponse)
+            .await
+            .map_err(BleError::from);
+        #[cfg(feature = "snoop")]
+        crate::snoop::write(c, data, WriteType::WithoutResponse, &result);
+        result?;
+        #[cfg(feature = "metrics")]
+        crate::metrics::sent(c, data.len(), start.elapsed());
         Ok(())
     }
 
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(skip_all, err, fields(charac = %c, bytes = Empty, device = Empty))
+    )]
     pub async fn recv_data(&mut self, c: Uuid) -> Result<Vec<u8>, BleError> {
         let dev = self.get_device().await?;
         let charac = self.get_charac(c)?;
-        let data = dev.read(charac).await?;
-        Ok(data)
+        #[cfg(feature = "metrics")]
+        let start = std::time::Instant::now();
+        let data = dev.read(charac).await.map_err(BleError::from);
+        #[cfg(feature = "snoop")]
+        crate::snoop::read(c, &data);
+        #[cfg(feature = "tracing")]
+        if let Ok(data) = &data {
+            tracing::Span::current().record("bytes", data.len());
+        }
+        #[cfg(feature = "metrics")]
+        if let Ok(data) = &data {
+            crate::metrics::received(c, data.len(), start.elapsed());
+        }
+        data
     }
 
     fn get_charac(&self, uuid: Uuid) -> Result<&Characteristic, BleError> {
         let charac = self.characs.iter().find(|c| c.uuid == uuid);
-        charac.ok_or(BleError::CharacNotAvailable(uuid.to_string()).into())
+        charac.ok_or(BleError::CharacNotAvailable(uuid.to_string()))
     }
 
     async fn get_device(&mut self) -> Result<Arc<Peripheral>, BleError> {
+        adapter::ensure_available()?;
         let dev = self.connected.as_ref().ok_or(BleError::NoDeviceConnected)?;
+        #[cfg(feature = "tracing")]
+        record_device(dev);
         if !dev.is_connected().await? {
-            self.disconnect().await?;
-            return Err(BleError::NoDeviceConnected.into());
+            self.disconnect(DisconnectReason::LinkLoss).await?;
+            Err(BleError::NoDeviceConnected)
         } else {
-            return Ok(dev.clone());
+            Ok(dev.clone())
         }
     }
 
@@ -214,6 +626,10 @@ impl BleHandler {
         Ok(connected)
     }
 
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(skip_all, err, fields(charac = %c, device = Empty))
+    )]
     pub async fn subscribe(
         &mut self,
         c: Uuid,
@@ -222,6 +638,8 @@ impl BleHandler {
         let dev = self.get_device().await?;
         let charac = self.get_charac(c)?;
         dev.subscribe(charac).await?;
+        #[cfg(feature = "snoop")]
+        crate::snoop::subscribe(charac);
         self.notify_listeners.lock().await.push(Listener {
             uuid: charac.uuid,
             callback: Arc::new(callback),
@@ -229,6 +647,10 @@ impl BleHandler {
         Ok(())
     }
 
+    pub(super) fn adapter(&self) -> Adapter {
+        self.adapter.clone()
+    }
+
     pub(super) async fn get_event_stream(
         &self,
     ) -> Result<Pin<Box<dyn Stream<Item = CentralEvent> + Send>>, BleError> {
@@ -237,16 +659,30 @@ impl BleHandler {
     }
 
     pub async fn handle_event(&mut self, event: CentralEvent) -> Result<(), BleError> {
-        // logi!("handling event {event:?}");
+        trace!("handling event {event:?}");
         match event {
-            CentralEvent::DeviceDisconnected(_) => self.disconnect().await,
+            CentralEvent::DeviceDisconnected(id) => {
+                // other devices disconnecting must not affect the connected one
+                if self.connected.as_ref().is_some_and(|dev| dev.id() == id) {
+                    self.disconnect(DisconnectReason::Remote).await?;
+                }
+                Ok(())
+            }
+            CentralEvent::StateUpdate(state) => {
+                let state = AdapterState::from(state);
+                debug!("adapter state changed to {state:?}");
+                if !state.is_available() && self.connected.is_some() {
+                    self.disconnect(DisconnectReason::AdapterOff).await?;
+                }
+                Ok(())
+            }
             _ => Ok(()),
         }
     }
 
     pub async fn connected_device(&self) -> Result<BleDevice, BleError> {
         let p = self.connected.as_ref().ok_or(BleError::NoDeviceConnected)?;
-        let d = BleDevice::from_peripheral(&p).await?;
+        let d = BleDevice::from_peripheral(p).await?;
         Ok(d)
     }
 }
@@ -258,7 +694,25 @@ async fn listen_notify(
     let mut stream = dev.notifications().await?;
     let rt = RUNTIME.get().ok_or(BleError::RuntimeNotInitialized)?;
     while let Some(data) = stream.next().await {
-        for l in listeners.lock().await.iter() {
+        trace!(
+            "notification of {} with {} bytes",
+            data.uuid,
+            data.value.len()
+        );
+        #[cfg(feature = "metrics")]
+        crate::metrics::notification(data.uuid, data.value.len());
+        #[cfg(feature = "snoop")]
+        crate::snoop::notification(data.uuid, &data.value);
+        let listeners = listeners.lock().await;
+        // recorded once, the replay delivers it to all listeners of the characteristic
+        #[cfg(feature = "record")]
+        if listeners.iter().any(|l| l.uuid == data.uuid) {
+            crate::record::record(|| crate::record::RecordedOp::Notification {
+                charac: data.uuid,
+                data: data.value.clone(),
+            });
+        }
+        for l in listeners.iter() {
             if l.uuid == data.uuid {
                 let data = data.value.clone();
                 let cb = l.callback.clone();
@@ -268,3 +722,9 @@ async fn listen_notify(
     }
     Ok(())
 }
+
+/// Adds the address of the device to the span of the current operation
+#[cfg(feature = "tracing")]
+fn record_device(device: &Peripheral) {
+    tracing::Span::current().record("device", tracing::field::display(device.address()));
+}
@@ -1,102 +1,364 @@
+mod adapter;
+mod address;
 pub mod ble;
+pub mod blocking;
+mod codec;
 mod error;
+mod events;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+#[cfg(feature = "frb")]
+pub mod frb;
+mod gatt;
 mod handler;
+#[cfg(feature = "metrics")]
+pub mod metrics;
+#[cfg(feature = "derive")]
+pub mod profile;
+#[cfg(feature = "python")]
+pub mod python;
+#[cfg(feature = "record")]
+pub mod record;
 mod setup;
+#[cfg(feature = "snoop")]
+pub mod snoop;
+#[cfg(feature = "uniffi")]
+pub mod uniffi_api;
+pub use adapter::{AdapterInfo, AdapterSelector, AdapterState};
+pub use address::*;
 pub use ble::*;
+#[cfg(feature = "derive")]
+pub use blec_derive::BleProfile;
 use btleplug::{
-    api::{BDAddr, Peripheral as _},
-    platform::Peripheral,
+    api::Peripheral as _,
+    platform::{Peripheral, PeripheralId},
 };
-pub use error::BleError;
+pub use codec::{BigEndian, CharacCodec, CodecError, Float, Sfloat};
+pub use error::{BleError, ParseBleAddressError};
+pub use events::BleEvent;
 use futures::Future;
+pub use gatt::{BleCharacteristic, BleService, CharacProperties};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+pub use setup::RuntimeConfig;
 use setup::RUNTIME;
+#[cfg(target_os = "android")]
+pub use setup::{on_thread_start, on_thread_stop};
+use std::collections::HashMap;
 use std::fmt::{self, Debug, Display, Formatter};
+use uuid::Uuid;
 
-#[derive(Debug, Clone, Ord, Eq)]
+#[cfg(feature = "uniffi")]
+uniffi::setup_scaffolding!();
+
+/// default timeout in milliseconds used by [connect] to scan for the device
+pub const DEFAULT_CONNECT_TIMEOUT: u64 = 5000;
+
+#[derive(Debug, Clone, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct BleDevice {
+    /// platform-stable identifier, use this to reconnect to the device
+    pub id: BleDeviceId,
+    /// zeroed on macOS/iOS as CoreBluetooth does not expose the address
     pub address: BleAddress,
+    /// public or random address, if reported by the platform
+    pub address_type: Option<BleAddressType>,
     pub name: String,
     pub is_connected: bool,
+    /// signal strength of the last advertisement
+    pub rssi: Option<i16>,
+    /// advertised services
+    pub services: Vec<Uuid>,
+    /// advertised manufacturer data by company id
+    pub manufacturer_data: HashMap<u16, Vec<u8>>,
+    /// advertised service data by service uuid
+    pub service_data: HashMap<Uuid, Vec<u8>>,
+}
+
+/// Devices are ordered by id, consistent with equality
+impl Ord for BleDevice {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.id.cmp(&other.id)
+    }
 }
 
 impl PartialOrd for BleDevice {
     fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
-        Some(self.address.cmp(&other.address))
+        Some(self.cmp(other))
     }
 }
 
 impl PartialEq for BleDevice {
     fn eq(&self, other: &Self) -> bool {
-        self.address == other.address
+        self.id == other.id
     }
 }
 
 impl BleDevice {
     async fn from_peripheral(peripheral: &Peripheral) -> Result<Self, BleError> {
+        Self::read_peripheral(peripheral, true).await
+    }
+
+    /// Same as [from_peripheral](BleDevice::from_peripheral) but devices without a local name get an empty name
+    async fn from_peripheral_unnamed(peripheral: &Peripheral) -> Result<Self, BleError> {
+        Self::read_peripheral(peripheral, false).await
+    }
+
+    async fn read_peripheral(
+        peripheral: &Peripheral,
+        require_name: bool,
+    ) -> Result<Self, BleError> {
+        let properties = peripheral.properties().await?.unwrap_or_default();
+        let name = match properties.local_name {
+            Some(name) => name,
+            None if !require_name => String::new(),
+            None => return Err(BleError::UnknownPeripheral(peripheral.id().to_string())),
+        };
         Ok(Self {
+            id: peripheral.id().into(),
             address: peripheral.address().into(),
-            name: peripheral
-                .properties()
-                .await?
-                .unwrap_or_default()
-                .local_name
-                .ok_or(BleError::UnknownPeripheral(peripheral.id().to_string()))?,
+            address_type: properties.address_type.map(Into::into),
+            name,
             is_connected: peripheral.is_connected().await?,
+            rssi: properties.rssi,
+            services: properties.services,
+            manufacturer_data: properties.manufacturer_data,
+            service_data: properties.service_data,
         })
     }
 }
 
+/// Selects devices by name, advertised services and signal strength.
+/// All fields that are set have to match.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct DiscoverFilter {
+    /// exact local name of the device
+    pub name: Option<String>,
+    /// prefix of the local name
+    pub name_prefix: Option<String>,
+    /// services the device has to advertise
+    pub services: Vec<Uuid>,
+    /// minimal rssi of the device
+    pub min_rssi: Option<i16>,
+}
+
+/// Decides if a discovered device should be used.
+/// Implemented for [DiscoverFilter] and closures taking a [BleDevice].
+pub trait DeviceFilter: Send + Sync + 'static {
+    fn matches(&self, device: &BleDevice) -> bool;
+}
+
+impl DeviceFilter for DiscoverFilter {
+    fn matches(&self, device: &BleDevice) -> bool {
+        if let Some(name) = &self.name {
+            if &device.name != name {
+                return false;
+            }
+        }
+        if let Some(prefix) = &self.name_prefix {
+            if !device.name.starts_with(prefix) {
+                return false;
+            }
+        }
+        if let Some(min_rssi) = self.min_rssi {
+            if device.rssi.is_none_or(|rssi| rssi < min_rssi) {
+                return false;
+            }
+        }
+        self.services.iter().all(|s| device.services.contains(s))
+    }
+}
+
+impl<F: Fn(&BleDevice) -> bool + Send + Sync + 'static> DeviceFilter for F {
+    fn matches(&self, device: &BleDevice) -> bool {
+        self(device)
+    }
+}
+
+/// Identifies a peripheral independent of the platform.
+/// On macOS/iOS this is a UUID assigned by CoreBluetooth, on other platforms it is based on the address.
+/// With the `serde` feature it can be persisted to reconnect to the device later.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize), serde(transparent))]
+pub struct BleDeviceId(DeviceIdKind);
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize), serde(untagged))]
+enum DeviceIdKind {
+    Platform(PeripheralId),
+    /// id of a replayed device, it was recorded on another platform or adapter
+    #[cfg(feature = "record")]
+    Recorded(String),
+}
+
+impl BleDeviceId {
+    #[cfg(feature = "record")]
+    pub(crate) fn recorded(id: String) -> Self {
+        Self(DeviceIdKind::Recorded(id))
+    }
+
+    fn is(&self, id: &PeripheralId) -> bool {
+        match &self.0 {
+            DeviceIdKind::Platform(platform) => platform == id,
+            #[cfg(feature = "record")]
+            DeviceIdKind::Recorded(_) => false,
+        }
+    }
+}
+
+impl From<PeripheralId> for BleDeviceId {
+    fn from(id: PeripheralId) -> Self {
+        Self(DeviceIdKind::Platform(id))
+    }
+}
+
+impl Display for BleDeviceId {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match &self.0 {
+            DeviceIdKind::Platform(id) => Display::fmt(id, f),
+            #[cfg(feature = "record")]
+            DeviceIdKind::Recorded(id) => f.write_str(id),
+        }
+    }
+}
+
+/// The device to connect to, either by address or by [BleDeviceId]
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum ConnectTarget {
+    Address(BleAddress),
+    Id(BleDeviceId),
+}
+
+impl ConnectTarget {
+    fn matches(&self, peripheral: &Peripheral) -> bool {
+        match self {
+            Self::Address(address) => *address == peripheral.address(),
+            Self::Id(id) => id.is(&peripheral.id()),
+        }
+    }
+}
+
+impl From<BleAddress> for ConnectTarget {
+    fn from(address: BleAddress) -> Self {
+        Self::Address(address)
+    }
+}
+
+impl From<BleDeviceId> for ConnectTarget {
+    fn from(id: BleDeviceId) -> Self {
+        Self::Id(id)
+    }
+}
+
+impl Display for ConnectTarget {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            Self::Address(address) => Display::fmt(address, f),
+            Self::Id(id) => Display::fmt(id, f),
+        }
+    }
+}
+
+/// Defines how the device to connect to is found
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum ConnectStrategy {
+    /// Only connect to peripherals already known to the adapter (e.g. bonded devices), never scan
+    Direct,
+    /// Scan until the device is found or the timeout in milliseconds elapsed.
+    /// Peripherals already known to the adapter are connected without scanning.
+    ScanUntilFound(u64),
+}
+
+impl Default for ConnectStrategy {
+    fn default() -> Self {
+        Self::ScanUntilFound(DEFAULT_CONNECT_TIMEOUT)
+    }
+}
+
+/// Configuration passed to [init_with_config]
+#[derive(Debug, Clone, Default)]
+pub struct InitConfig {
+    /// adapter to use, can be changed later with [select_adapter]
+    pub adapter: AdapterSelector,
+    /// runtime blec runs on, a single threaded runtime is created by default
+    pub runtime: RuntimeConfig,
+}
+
+/// Why a connection ended, passed to the disconnect callback
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "uniffi", derive(uniffi::Enum))]
+#[cfg_attr(feature = "python", pyo3::pyclass(eq, eq_int, module = "blec"))]
+pub enum DisconnectReason {
+    /// [disconnect] was called, another device was connected or the adapter was switched
+    UserInitiated,
+    /// the platform reported the disconnect, e.g. because the device closed the connection
+    Remote,
+    /// the connection was found to be lost when using it
+    LinkLoss,
+    /// the adapter was turned off or became unavailable
+    AdapterOff,
+}
+
 /// spawn future on the internally initialized runtime
 pub fn spawn<F>(f: F) -> Result<(), BleError>
 where
     F: Future + Send + 'static,
     F::Output: Send,
 {
+    setup::check_setup_error()?;
     let rt = RUNTIME.get().ok_or(BleError::RuntimeNotInitialized)?;
+    // keeps the span of the caller
+    #[cfg(feature = "tracing")]
+    let f = tracing::Instrument::in_current_span(f);
     rt.spawn(f);
     Ok(())
 }
 
-/// block on an async operation
+/// block on an async operation.
+/// Fails with [BleError::BlockingInAsyncContext] if blocking would panic or deadlock the current runtime.
 pub fn block_on<F: Future>(f: F) -> Result<F::Output, BleError> {
+    setup::check_setup_error()?;
     let rt = RUNTIME.get().ok_or(BleError::RuntimeNotInitialized)?;
+    if !rt.can_block() {
+        return Err(BleError::BlockingInAsyncContext);
+    }
     Ok(rt.block_on(f))
 }
 
-#[derive(Debug, Clone, Copy, Ord, Eq, PartialOrd, PartialEq, Hash, Default)]
-pub struct BleAddress {
-    pub address: [u8; 6],
-}
-impl PartialEq<BDAddr> for BleAddress {
-    fn eq(&self, other: &BDAddr) -> bool {
-        self.address.eq(&other.into_inner())
-    }
-}
-impl From<BDAddr> for BleAddress {
-    fn from(addr: BDAddr) -> Self {
-        Self {
-            address: addr.into_inner(),
+#[cfg(all(test, feature = "record"))]
+mod tests {
+    use super::*;
+    use std::cmp::Ordering;
+
+    fn device(id: &str, address: u8) -> BleDevice {
+        BleDevice {
+            id: BleDeviceId::recorded(id.to_string()),
+            address: BleAddress {
+                address: [address; 6],
+            },
+            address_type: None,
+            name: String::new(),
+            is_connected: false,
+            rssi: None,
+            services: vec![],
+            manufacturer_data: HashMap::new(),
+            service_data: HashMap::new(),
         }
     }
-}
-impl Display for BleAddress {
-    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
-        let a = &self.address;
-        write!(
-            f,
-            "{:02X}:{:02X}:{:02X}:{:02X}:{:02X}:{:02X}",
-            a[0], a[1], a[2], a[3], a[4], a[5]
-        )
-    }
-}
-impl BleAddress {
-    // Parses a Bluetooth address with colons `:` as delimiters.
-    pub fn from_str_delim(addr_str: &str) -> Result<Self, ParseBleAddressError> {
-        match BDAddr::from_str_delim(addr_str) {
-            Ok(addr) => Ok(addr.into()),
-            Err(_) => Err(ParseBleAddressError),
-        }
+
+    #[test]
+    fn ordering_agrees_with_equality() {
+        // e.g. a device that changed its random address
+        let (a, b) = (device("dev", 1), device("dev", 2));
+        assert_eq!(a, b);
+        assert_eq!(a.cmp(&b), Ordering::Equal);
+        let other = device("other", 0);
+        assert_ne!(a, other);
+        assert_eq!(a.cmp(&other), Ordering::Less);
+        assert_eq!(other.cmp(&b), Ordering::Greater);
     }
 }
-#[derive(Debug, Clone, Copy)]
-pub struct ParseBleAddressError;
@@ -0,0 +1,183 @@
+//! Counters of the operations done by blec and a hook to forward them to a monitoring system.
+//!
+//! Rates like notifications per second are derived from the difference of two [metrics] snapshots.
+
+use crate::{BleAddress, BleDeviceId, BleError, DisconnectReason};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::Duration;
+use uuid::Uuid;
+
+/// Snapshot of the counters since the start or the last [reset_metrics]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Metrics {
+    /// established connections, including reconnects
+    pub connections: u64,
+    pub disconnects: u64,
+    /// connect attempts to the device that was connected or tried to connect to last,
+    /// this includes retries of a failed first connect
+    pub reconnect_attempts: u64,
+    pub bytes_sent: u64,
+    /// bytes read and received with notifications
+    pub bytes_received: u64,
+    pub notifications: u64,
+    /// failed operations
+    pub errors: u64,
+}
+
+/// Passed to the hook set with [set_metrics_hook] for every counted event.
+/// Events of operations carry how long the operation took.
+#[derive(Debug, Clone, Copy)]
+pub enum MetricEvent<'a> {
+    Connected {
+        address: BleAddress,
+        duration: Duration,
+    },
+    Disconnected {
+        reason: DisconnectReason,
+    },
+    ReconnectAttempt {
+        address: BleAddress,
+    },
+    Sent {
+        charac: Uuid,
+        bytes: usize,
+        duration: Duration,
+    },
+    Received {
+        charac: Uuid,
+        bytes: usize,
+        duration: Duration,
+    },
+    Notification {
+        charac: Uuid,
+        bytes: usize,
+    },
+    Error(&'a BleError),
+}
+
+type Hook = Arc<dyn Fn(&MetricEvent) + Send + Sync>;
+
+static CONNECTIONS: AtomicU64 = AtomicU64::new(0);
+static DISCONNECTS: AtomicU64 = AtomicU64::new(0);
+static RECONNECT_ATTEMPTS: AtomicU64 = AtomicU64::new(0);
+static BYTES_SENT: AtomicU64 = AtomicU64::new(0);
+static BYTES_RECEIVED: AtomicU64 = AtomicU64::new(0);
+static NOTIFICATIONS: AtomicU64 = AtomicU64::new(0);
+static ERRORS: AtomicU64 = AtomicU64::new(0);
+static HOOK: RwLock<Option<Hook>> = RwLock::new(None);
+/// device connected last, connecting to it again counts as reconnect attempt
+static LAST_CONNECTED: Mutex<Option<BleDeviceId>> = Mutex::new(None);
+/// device of the last connect attempt, trying it again after a failure counts as well
+static LAST_ATTEMPTED: Mutex<Option<BleDeviceId>> = Mutex::new(None);
+
+pub fn metrics() -> Metrics {
+    Metrics {
+        connections: CONNECTIONS.load(Ordering::Relaxed),
+        disconnects: DISCONNECTS.load(Ordering::Relaxed),
+        reconnect_attempts: RECONNECT_ATTEMPTS.load(Ordering::Relaxed),
+        bytes_sent: BYTES_SENT.load(Ordering::Relaxed),
+        bytes_received: BYTES_RECEIVED.load(Ordering::Relaxed),
+        notifications: NOTIFICATIONS.load(Ordering::Relaxed),
+        errors: ERRORS.load(Ordering::Relaxed),
+    }
+}
+
+pub fn reset_metrics() {
+    for counter in [
+        &CONNECTIONS,
+        &DISCONNECTS,
+        &RECONNECT_ATTEMPTS,
+        &BYTES_SENT,
+        &BYTES_RECEIVED,
+        &NOTIFICATIONS,
+        &ERRORS,
+    ] {
+        counter.store(0, Ordering::Relaxed);
+    }
+}
+
+/// Sets the hook called for every counted event, replacing the previous one.
+/// It is called directly from the blec runtime and must not block.
+pub fn set_metrics_hook(hook: impl Fn(&MetricEvent) + Send + Sync + 'static) {
+    *HOOK.write().unwrap_or_else(|e| e.into_inner()) = Some(Arc::new(hook));
+}
+
+pub fn remove_metrics_hook() {
+    HOOK.write().unwrap_or_else(|e| e.into_inner()).take();
+}
+
+fn count(counter: &AtomicU64, amount: u64, event: MetricEvent) {
+    counter.fetch_add(amount, Ordering::Relaxed);
+    // cloned so the hook can replace itself
+    let hook = HOOK.read().unwrap_or_else(|e| e.into_inner()).clone();
+    if let Some(hook) = hook {
+        hook(&event);
+    }
+}
+
+pub(crate) fn connecting(id: &BleDeviceId, address: BleAddress) {
+    let attempted = LAST_ATTEMPTED
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .replace(id.clone());
+    let connected = LAST_CONNECTED.lock().unwrap_or_else(|e| e.into_inner());
+    if connected.as_ref() == Some(id) || attempted.as_ref() == Some(id) {
+        drop(connected);
+        count(
+            &RECONNECT_ATTEMPTS,
+            1,
+            MetricEvent::ReconnectAttempt { address },
+        );
+    }
+}
+
+pub(crate) fn connected(id: BleDeviceId, address: BleAddress, duration: Duration) {
+    *LAST_CONNECTED.lock().unwrap_or_else(|e| e.into_inner()) = Some(id);
+    count(
+        &CONNECTIONS,
+        1,
+        MetricEvent::Connected { address, duration },
+    );
+}
+
+pub(crate) fn disconnected(reason: DisconnectReason) {
+    count(&DISCONNECTS, 1, MetricEvent::Disconnected { reason });
+}
+
+pub(crate) fn sent(charac: Uuid, bytes: usize, duration: Duration) {
+    count(
+        &BYTES_SENT,
+        bytes as u64,
+        MetricEvent::Sent {
+            charac,
+            bytes,
+            duration,
+        },
+    );
+}
+
+pub(crate) fn received(charac: Uuid, bytes: usize, duration: Duration) {
+    count(
+        &BYTES_RECEIVED,
+        bytes as u64,
+        MetricEvent::Received {
+            charac,
+            bytes,
+            duration,
+        },
+    );
+}
+
+pub(crate) fn notification(charac: Uuid, bytes: usize) {
+    BYTES_RECEIVED.fetch_add(bytes as u64, Ordering::Relaxed);
+    count(
+        &NOTIFICATIONS,
+        1,
+        MetricEvent::Notification { charac, bytes },
+    );
+}
+
+pub(crate) fn error(error: &BleError) {
+    count(&ERRORS, 1, MetricEvent::Error(error));
+}
@@ -0,0 +1,222 @@
+//! Support for clients generated with [BleProfile](crate::BleProfile).
+//!
+//! ```ignore
+//! #[derive(BleProfile)]
+//! #[ble(service = "0000180f-0000-1000-8000-00805f9b34fb")]
+//! struct Battery {
+//!     #[ble(charac = "00002a19-0000-1000-8000-00805f9b34fb", read, notify)]
+//!     level: u8,
+//!     #[ble(charac = "12345678-1234-5678-1234-56789abcdef0", write, codec = BigEndian<u16>)]
+//!     threshold: u16,
+//! }
+//!
+//! let battery = BatteryClient::connect(address, None::<fn(_, _)>).await?;
+//! let level = battery.read_level().await?;
+//! battery.write_threshold(1000).await?;
+//! ```
+//!
+//! The derive generates uuid constants on the struct (`Battery::SERVICE`, `Battery::LEVEL`, ...) and a
+//! `<Name>Client` with `read_<field>`, `write_<field>` and `subscribe_<field>` for the declared properties.
+//! `read_all` returns the whole struct if all fields are readable.
+//! Values are converted with [CharacCodec](crate::CharacCodec), `codec` selects another codec
+//! that converts from and into the field type.
+
+use crate::{ble, BleDevice, BleError, CharacProperties, ConnectTarget, DisconnectReason};
+#[cfg(not(feature = "tracing"))]
+use log::warn;
+#[cfg(feature = "tracing")]
+use tracing::warn;
+/// re-exported for the generated code
+pub use uuid::Uuid;
+
+/// Characteristic of a profile and the properties it has to support
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Requirement {
+    pub uuid: Uuid,
+    pub read: bool,
+    /// with or without response
+    pub write: bool,
+    /// notify or indicate
+    pub notify: bool,
+}
+
+impl Requirement {
+    /// Name of the first required property that is not supported
+    fn missing(&self, properties: &CharacProperties) -> Option<&'static str> {
+        if self.read && !properties.read {
+            return Some("read");
+        }
+        if self.write && !(properties.write || properties.write_without_response) {
+            return Some("write");
+        }
+        if self.notify && !(properties.notify || properties.indicate) {
+            return Some("notify");
+        }
+        None
+    }
+}
+
+/// Connects to the service and checks that the device supports the required characteristics and properties.
+/// Disconnects again if it doesn't.
+pub async fn connect_profile(
+    target: impl Into<ConnectTarget>,
+    service: Uuid,
+    requirements: &[Requirement],
+    on_disconnect: Option<impl FnOnce(BleDevice, DisconnectReason) + Send + 'static>,
+) -> Result<(), BleError> {
+    let characs = requirements.iter().map(|r| r.uuid).collect();
+    // the callback is installed after the check, the cleanup disconnect must not call it
+    ble::connect(target, service, characs, None::<fn(_, _)>).await?;
+    if let Err(e) = check_requirements(service, requirements).await {
+        // the unsupported device is the error worth reporting
+        if let Err(disconnect_error) = ble::disconnect().await {
+            warn!("could not disconnect the unsupported device: {disconnect_error}");
+        }
+        return Err(e);
+    }
+    if let Some(on_disconnect) = on_disconnect {
+        ble::set_on_disconnect(on_disconnect).await?;
+    }
+    Ok(())
+}
+
+async fn check_requirements(service: Uuid, requirements: &[Requirement]) -> Result<(), BleError> {
+    let services = ble::services().await?;
+    let characs: Vec<_> = services
+        .iter()
+        .filter(|s| s.uuid == service)
+        .flat_map(|s| &s.characteristics)
+        .collect();
+    for requirement in requirements {
+        let charac = characs
+            .iter()
+            .find(|c| c.uuid == requirement.uuid)
+            .ok_or(BleError::CharacNotAvailable(requirement.uuid.to_string()))?;
+        if let Some(property) = requirement.missing(&charac.properties) {
+            return Err(BleError::MissingProperty {
+                charac: requirement.uuid,
+                property,
+            });
+        }
+    }
+    Ok(())
+}
+
+#[cfg(all(test, feature = "record"))]
+mod tests {
+    use super::*;
+    use crate::record::{
+        self, RecordEntry, RecordedDevice, RecordedOp, RecordedTarget, Recording, ReplayConfig,
+    };
+    use crate::{BleAddress, BleCharacteristic, BleService};
+    use std::sync::mpsc;
+    use std::time::Duration;
+
+    const SERVICE: Uuid = Uuid::from_u128(0x180f);
+    const LEVEL: Uuid = Uuid::from_u128(0x2a19);
+    const ADDRESS: BleAddress = BleAddress {
+        address: [0xC0, 1, 2, 3, 4, 5],
+    };
+
+    /// A device whose battery level can only be read, it is disconnected with `reason`
+    fn recording(reason: DisconnectReason) -> Recording {
+        let ops = [
+            RecordedOp::Connect {
+                target: Some(RecordedTarget::Address(ADDRESS)),
+                result: Ok(()),
+                device: Some(RecordedDevice {
+                    id: ADDRESS.to_string(),
+                    address: ADDRESS,
+                    address_type: None,
+                    name: "Battery".to_string(),
+                    is_connected: true,
+                    rssi: None,
+                    services: vec![SERVICE],
+                    manufacturer_data: Default::default(),
+                    service_data: Default::default(),
+                }),
+            },
+            RecordedOp::Services {
+                result: Ok(vec![BleService {
+                    uuid: SERVICE,
+                    primary: true,
+                    characteristics: vec![BleCharacteristic {
+                        uuid: LEVEL,
+                        service: SERVICE,
+                        properties: CharacProperties {
+                            read: true,
+                            ..Default::default()
+                        },
+                        descriptors: vec![],
+                    }],
+                }]),
+            },
+            RecordedOp::Disconnected { reason },
+        ];
+        Recording {
+            version: record::RECORDING_VERSION,
+            entries: ops
+                .into_iter()
+                .zip([0, 0, 100])
+                .map(|(op, at)| RecordEntry { at, op })
+                .collect(),
+        }
+    }
+
+    fn connect(notify: bool) -> (Result<(), BleError>, mpsc::Receiver<DisconnectReason>) {
+        let (tx, rx) = mpsc::channel();
+        let requirement = Requirement {
+            uuid: LEVEL,
+            read: true,
+            write: false,
+            notify,
+        };
+        let result = crate::block_on(connect_profile(
+            ADDRESS,
+            SERVICE,
+            &[requirement],
+            Some(move |_, reason| {
+                let _ = tx.send(reason);
+            }),
+        ))
+        .unwrap();
+        (result, rx)
+    }
+
+    #[test]
+    fn failed_requirements_do_not_call_on_disconnect() {
+        let _serial = record::tests::SERIAL
+            .lock()
+            .unwrap_or_else(|e| e.into_inner());
+        let recording = recording(DisconnectReason::UserInitiated);
+        record::start_replay(recording, record::tests::no_timing()).unwrap();
+        let (result, disconnected) = connect(true);
+        assert!(matches!(
+            result,
+            Err(BleError::MissingProperty {
+                charac: LEVEL,
+                property: "notify"
+            })
+        ));
+        // the cleanup disconnect was replayed
+        record::stop_replay().unwrap();
+        assert!(disconnected
+            .recv_timeout(Duration::from_millis(200))
+            .is_err());
+    }
+
+    #[test]
+    fn on_disconnect_is_called_after_a_successful_check() {
+        let _serial = record::tests::SERIAL
+            .lock()
+            .unwrap_or_else(|e| e.into_inner());
+        let recording = recording(DisconnectReason::Remote);
+        // with timing the disconnect is replayed after the callback was installed
+        record::start_replay(recording, ReplayConfig::default()).unwrap();
+        let (result, disconnected) = connect(false);
+        result.unwrap();
+        let reason = disconnected.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert_eq!(reason, DisconnectReason::Remote);
+        record::stop_replay().unwrap();
+    }
+}
@@ -0,0 +1,314 @@
+//! Python bindings built with [PyO3](https://pyo3.rs).
+//!
+//! Async functions return asyncio futures completed from the blec runtime. blec does not define the extension module
+//! itself, register the functions in the `#[pymodule]` of a `cdylib` crate built with maturin:
+//!
+//! ```ignore
+//! #[pymodule]
+//! fn blec(m: &Bound<'_, PyModule>) -> PyResult<()> {
+//!     ::blec::python::register(m)
+//! }
+//! ```
+
+use crate::{ble, AdapterState, BleError, DisconnectReason};
+#[cfg(not(feature = "tracing"))]
+use log::error;
+use pyo3::create_exception;
+use pyo3::exceptions::PyException;
+use pyo3::prelude::*;
+use pyo3::IntoPyObjectExt;
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::time::Duration;
+#[cfg(feature = "tracing")]
+use tracing::error;
+use uuid::Uuid;
+
+create_exception!(
+    blec,
+    BleException,
+    PyException,
+    "Raised for all errors reported by blec"
+);
+
+impl From<BleError> for PyErr {
+    fn from(e: BleError) -> Self {
+        BleException::new_err(e.to_string())
+    }
+}
+
+/// [BleDevice](crate::BleDevice) with uuids, addresses and ids as strings
+#[pyclass(name = "BleDevice", module = "blec", frozen, get_all)]
+#[derive(Debug, Clone)]
+pub struct PyBleDevice {
+    pub id: String,
+    pub address: String,
+    pub name: String,
+    pub is_connected: bool,
+    pub rssi: Option<i16>,
+    pub services: Vec<String>,
+    pub manufacturer_data: HashMap<u16, Vec<u8>>,
+    pub service_data: HashMap<String, Vec<u8>>,
+}
+
+#[pymethods]
+impl PyBleDevice {
+    fn __repr__(&self) -> String {
+        format!(
+            "BleDevice(name={:?}, address={:?}, rssi={:?})",
+            self.name, self.address, self.rssi
+        )
+    }
+}
+
+impl From<crate::BleDevice> for PyBleDevice {
+    fn from(device: crate::BleDevice) -> Self {
+        Self {
+            id: device.id.to_string(),
+            address: device.address.to_string(),
+            name: device.name,
+            is_connected: device.is_connected,
+            rssi: device.rssi,
+            services: device.services.iter().map(Uuid::to_string).collect(),
+            manufacturer_data: device.manufacturer_data,
+            service_data: device
+                .service_data
+                .into_iter()
+                .map(|(uuid, data)| (uuid.to_string(), data))
+                .collect(),
+        }
+    }
+}
+
+/// set when the interpreter shuts down, blec threads must not use python afterwards
+static PYTHON_EXITING: AtomicBool = AtomicBool::new(false);
+/// number of blec threads currently using python
+static PYTHON_CALLS: AtomicUsize = AtomicUsize::new(0);
+
+/// Runs `f` with the GIL from a blec thread, does nothing once the interpreter is shutting down.
+/// Threads still holding the GIL during finalization would crash the interpreter.
+fn with_python(f: impl for<'py> FnOnce(Python<'py>)) {
+    PYTHON_CALLS.fetch_add(1, Ordering::SeqCst);
+    if !PYTHON_EXITING.load(Ordering::SeqCst) {
+        Python::with_gil(f);
+    }
+    PYTHON_CALLS.fetch_sub(1, Ordering::SeqCst);
+}
+
+/// Registered with `atexit`, waits for blec threads to stop using python
+#[pyfunction]
+fn on_exit(py: Python<'_>) {
+    PYTHON_EXITING.store(true, Ordering::SeqCst);
+    py.allow_threads(|| {
+        while PYTHON_CALLS.load(Ordering::SeqCst) > 0 {
+            std::thread::sleep(Duration::from_millis(1));
+        }
+    });
+}
+
+/// Calls a python callback, exceptions raised by it are logged as there is nobody to report them to
+fn call_python(
+    callback: &Py<PyAny>,
+    args: impl for<'py> IntoPyObject<'py, Target = pyo3::types::PyTuple>,
+) {
+    with_python(|py| {
+        if let Err(e) = callback.call1(py, args) {
+            error!("python callback failed: {e}");
+        }
+    });
+}
+
+/// Sets the result or exception of the asyncio future, unless it was cancelled in the meantime
+#[pyfunction]
+fn complete(
+    future: Bound<'_, PyAny>,
+    result: Bound<'_, PyAny>,
+    error: Option<Bound<'_, PyAny>>,
+) -> PyResult<()> {
+    if future.call_method0("done")?.extract()? {
+        return Ok(());
+    }
+    match error {
+        Some(e) => future.call_method1("set_exception", (e,))?,
+        None => future.call_method1("set_result", (result,))?,
+    };
+    Ok(())
+}
+
+/// Runs the future on the blec runtime and returns an asyncio future for its result.
+/// Has to be called from a coroutine running in an event loop.
+fn into_future<F, T>(py: Python<'_>, fut: F) -> PyResult<Bound<'_, PyAny>>
+where
+    F: Future<Output = Result<T, BleError>> + Send + 'static,
+    T: for<'py> IntoPyObject<'py> + Send + 'static,
+{
+    let event_loop = py.import("asyncio")?.call_method0("get_running_loop")?;
+    let future = event_loop.call_method0("create_future")?;
+    let (event_loop, future_ref) = (event_loop.unbind(), future.clone().unbind());
+    crate::spawn(async move {
+        let result = fut.await;
+        // all python objects are used and dropped while holding the GIL
+        with_python(move |py| {
+            let (value, error) = match result.map_err(PyErr::from).and_then(|v| v.into_py_any(py)) {
+                Ok(value) => (value, None),
+                Err(e) => (py.None(), Some(e.into_value(py))),
+            };
+            let scheduled = wrap_pyfunction!(complete, py).and_then(|complete| {
+                event_loop.call_method1(
+                    py,
+                    "call_soon_threadsafe",
+                    (complete, future_ref, value, error),
+                )
+            });
+            // fails if the event loop was closed, then nobody waits for the result
+            if let Err(e) = scheduled {
+                error!("could not complete python future: {e}");
+            }
+        });
+    })?;
+    Ok(future)
+}
+
+/// Initializes blec, has to be called before anything else
+#[pyfunction]
+fn init(py: Python<'_>) -> PyResult<()> {
+    // blocks until the adapter is set up, other python threads keep running meanwhile
+    Ok(py.allow_threads(crate::init)?)
+}
+
+#[pyfunction]
+fn deinit(py: Python<'_>) -> PyResult<()> {
+    Ok(py.allow_threads(crate::deinit)?)
+}
+
+#[pyfunction]
+fn adapter_state() -> AdapterState {
+    ble::adapter_state()
+}
+
+/// Scans for `timeout_ms` milliseconds and returns the discovered devices
+#[pyfunction]
+fn discover(py: Python<'_>, timeout_ms: u64) -> PyResult<Bound<'_, PyAny>> {
+    into_future(py, async move {
+        let devices = ble::discover_async(timeout_ms).await?;
+        Ok(devices
+            .into_iter()
+            .map(PyBleDevice::from)
+            .collect::<Vec<_>>())
+    })
+}
+
+/// Connects to the device with the given address or device id.
+/// `on_disconnect` is called with the device and the [DisconnectReason] once the connection ends.
+#[pyfunction]
+#[pyo3(signature = (target, service, characs, on_disconnect=None))]
+fn connect(
+    py: Python<'_>,
+    target: String,
+    service: String,
+    characs: Vec<String>,
+    on_disconnect: Option<Py<PyAny>>,
+) -> PyResult<Bound<'_, PyAny>> {
+    into_future(py, async move {
+        let target = ble::parse_target(target).await?;
+        let service = Uuid::parse_str(&service)?;
+        let characs = ble::parse_uuids(&characs)?;
+        let on_disconnect = on_disconnect.map(|callback| {
+            move |device: crate::BleDevice, reason: DisconnectReason| {
+                call_python(&callback, (PyBleDevice::from(device), reason))
+            }
+        });
+        ble::connect(target, service, characs, on_disconnect).await
+    })
+}
+
+#[pyfunction]
+fn disconnect(py: Python<'_>) -> PyResult<Bound<'_, PyAny>> {
+    into_future(py, ble::disconnect())
+}
+
+/// Reads the characteristic and returns its value as bytes
+#[pyfunction]
+fn recv_data(py: Python<'_>, charac: String) -> PyResult<Bound<'_, PyAny>> {
+    into_future(py, async move {
+        let charac = Uuid::parse_str(&charac)?;
+        ble::recv_data(charac).await
+    })
+}
+
+#[pyfunction]
+fn send_data(py: Python<'_>, charac: String, data: Vec<u8>) -> PyResult<Bound<'_, PyAny>> {
+    into_future(py, async move {
+        let charac = Uuid::parse_str(&charac)?;
+        ble::send_data(charac, data).await
+    })
+}
+
+/// Calls `callback` with the value of every notification of the characteristic
+#[pyfunction]
+fn subscribe(py: Python<'_>, charac: String, callback: Py<PyAny>) -> PyResult<Bound<'_, PyAny>> {
+    into_future(py, async move {
+        let charac = Uuid::parse_str(&charac)?;
+        let callback = move |data: &[u8]| call_python(&callback, (data.to_vec(),));
+        ble::subscribe(charac, callback).await
+    })
+}
+
+#[pyfunction]
+fn is_connected(py: Python<'_>) -> PyResult<Bound<'_, PyAny>> {
+    into_future(py, ble::is_connected())
+}
+
+#[pyfunction]
+fn connected_device(py: Python<'_>) -> PyResult<Bound<'_, PyAny>> {
+    into_future(py, async move {
+        Ok(PyBleDevice::from(ble::connected_device().await?))
+    })
+}
+
+/// Replays the recording at `path` (JSON, or CBOR if it ends with `.cbor`) instead of using the adapter,
+/// see [record](crate::record)
+#[cfg(feature = "record")]
+#[pyfunction]
+#[pyo3(signature = (path, strict=true, timing=true))]
+fn start_replay(path: std::path::PathBuf, strict: bool, timing: bool) -> PyResult<()> {
+    let recording = crate::record::Recording::load(path)?;
+    let config = crate::record::ReplayConfig { strict, timing };
+    Ok(crate::record::start_replay(recording, config)?)
+}
+
+/// Stops the replay, raises if it diverged from the recording in strict mode
+#[cfg(feature = "record")]
+#[pyfunction]
+fn stop_replay() -> PyResult<()> {
+    Ok(crate::record::stop_replay()?)
+}
+
+/// Adds the blec functions and classes to the python module
+pub fn register(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.py()
+        .import("atexit")?
+        .call_method1("register", (wrap_pyfunction!(on_exit, m)?,))?;
+    m.add("BleException", m.py().get_type::<BleException>())?;
+    m.add_class::<PyBleDevice>()?;
+    m.add_class::<DisconnectReason>()?;
+    m.add_class::<AdapterState>()?;
+    m.add_function(wrap_pyfunction!(init, m)?)?;
+    m.add_function(wrap_pyfunction!(deinit, m)?)?;
+    m.add_function(wrap_pyfunction!(adapter_state, m)?)?;
+    m.add_function(wrap_pyfunction!(discover, m)?)?;
+    m.add_function(wrap_pyfunction!(connect, m)?)?;
+    m.add_function(wrap_pyfunction!(disconnect, m)?)?;
+    m.add_function(wrap_pyfunction!(recv_data, m)?)?;
+    m.add_function(wrap_pyfunction!(send_data, m)?)?;
+    m.add_function(wrap_pyfunction!(subscribe, m)?)?;
+    m.add_function(wrap_pyfunction!(is_connected, m)?)?;
+    m.add_function(wrap_pyfunction!(connected_device, m)?)?;
+    #[cfg(feature = "record")]
+    {
+        m.add_function(wrap_pyfunction!(start_replay, m)?)?;
+        m.add_function(wrap_pyfunction!(stop_replay, m)?)?;
+    }
+    Ok(())
+}
@@ -0,0 +1,968 @@
+//! Recording of sessions and replaying them without a bluetooth adapter, e.g. to run regression tests in CI.
+//!
+//! While recording, every operation done through the blec API is stored with its result,
+//! as well as notifications and disconnects reported by the device.
+//! During a replay the same API serves the recorded results in order and delivers
+//! notifications and disconnects with the recorded timing, [init](crate::init) is not needed.
+//!
+//! ```ignore
+//! blec::record::start_recording();
+//! // ... use blec as usual
+//! blec::record::stop_recording().unwrap().save("session.json")?;
+//!
+//! // in the test
+//! blec::record::start_replay(Recording::load("session.json")?, ReplayConfig::default())?;
+//! // ... run the same code, it fails with BleError::ReplayDiverged if it does something else
+//! blec::record::stop_replay()?;
+//! ```
+
+use crate::setup::{self, RUNTIME};
+use crate::{
+    BleAddress, BleAddressType, BleDevice, BleDeviceId, BleError, BleService, ConnectTarget,
+    DisconnectReason, ParseBleAddressError, RuntimeConfig,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::{Arc, Mutex, MutexGuard, RwLock};
+use std::time::{Duration, Instant};
+use tokio::sync::Notify;
+use tokio::task::AbortHandle;
+use uuid::Uuid;
+
+/// version of the format written by [Recording::to_json] and [Recording::to_cbor]
+pub const RECORDING_VERSION: u32 = 1;
+
+type NotifyCallback = Arc<dyn Fn(&[u8]) + Send + Sync>;
+pub(crate) type DisconnectCallback = Box<dyn FnOnce(BleDevice, DisconnectReason) + Send>;
+
+/// A recorded session, serialized as JSON or CBOR
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Recording {
+    pub version: u32,
+    pub entries: Vec<RecordEntry>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RecordEntry {
+    /// milliseconds since the recording was started
+    pub at: u64,
+    pub op: RecordedOp,
+}
+
+/// Result of a recorded operation
+pub type RecordedResult<T> = Result<T, RecordedError>;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum RecordedOp {
+    Discover {
+        result: RecordedResult<Vec<RecordedDevice>>,
+    },
+    Connect {
+        /// `None` if the device was selected by a filter
+        target: Option<RecordedTarget>,
+        result: RecordedResult<()>,
+        /// the connected device, if it could be read
+        device: Option<RecordedDevice>,
+    },
+    Services {
+        result: RecordedResult<Vec<BleService>>,
+    },
+    Read {
+        charac: Uuid,
+        result: RecordedResult<Vec<u8>>,
+    },
+    Write {
+        charac: Uuid,
+        data: Vec<u8>,
+        result: RecordedResult<()>,
+    },
+    Subscribe {
+        charac: Uuid,
+        result: RecordedResult<()>,
+    },
+    /// notification received from the device
+    Notification { charac: Uuid, data: Vec<u8> },
+    /// the connection ended, [DisconnectReason::UserInitiated] if the application disconnected
+    Disconnected { reason: DisconnectReason },
+}
+
+/// A [BleDevice] independent of the platform it was recorded on.
+/// Replayed devices get a [BleDeviceId] that can only be used during the replay.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RecordedDevice {
+    /// string representation of the [BleDeviceId]
+    pub id: String,
+    pub address: BleAddress,
+    pub address_type: Option<BleAddressType>,
+    pub name: String,
+    pub is_connected: bool,
+    pub rssi: Option<i16>,
+    pub services: Vec<Uuid>,
+    pub manufacturer_data: HashMap<u16, Vec<u8>>,
+    pub service_data: HashMap<Uuid, Vec<u8>>,
+}
+
+impl From<&BleDevice> for RecordedDevice {
+    fn from(device: &BleDevice) -> Self {
+        Self {
+            id: device.id.to_string(),
+            address: device.address,
+            address_type: device.address_type,
+            name: device.name.clone(),
+            is_connected: device.is_connected,
+            rssi: device.rssi,
+            services: device.services.clone(),
+            manufacturer_data: device.manufacturer_data.clone(),
+            service_data: device.service_data.clone(),
+        }
+    }
+}
+
+impl From<RecordedDevice> for BleDevice {
+    fn from(device: RecordedDevice) -> Self {
+        Self {
+            id: BleDeviceId::recorded(device.id),
+            address: device.address,
+            address_type: device.address_type,
+            name: device.name,
+            is_connected: device.is_connected,
+            rssi: device.rssi,
+            services: device.services,
+            manufacturer_data: device.manufacturer_data,
+            service_data: device.service_data,
+        }
+    }
+}
+
+/// A [ConnectTarget] with the id stored as string
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RecordedTarget {
+    Address(BleAddress),
+    Id(String),
+}
+
+impl From<&ConnectTarget> for RecordedTarget {
+    fn from(target: &ConnectTarget) -> Self {
+        match target {
+            ConnectTarget::Address(address) => Self::Address(*address),
+            ConnectTarget::Id(id) => Self::Id(id.to_string()),
+        }
+    }
+}
+
+/// Error of a recorded operation, replayed as the matching [BleError].
+/// The first variants are errors of btleplug.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum RecordedError {
+    PermissionDenied,
+    DeviceNotFound,
+    NotConnected,
+    NotSupported(String),
+    TimedOut(Duration),
+    RuntimeError(String),
+    RuntimeNotInitialized,
+    UnknownPeripheral(String),
+    CharacNotFound(Uuid),
+    CharacNotAvailable(String),
+    NoDeviceConnected,
+    ServiceNotFound,
+    AlreadyConnected,
+    HandlerNotInitialized,
+    NoMatchingDevice,
+    NoAdapters,
+    AdapterOff,
+    AdapterNotFound(String),
+    InvalidAddress(ParseBleAddressError),
+    JniSetup(String),
+    BlockingInAsyncContext,
+    HandlerAlreadyInitialized,
+    SendingDevices,
+    SinkClosed,
+    /// any other error, only its message is kept and it is replayed as [BleError::Replayed]
+    Other(String),
+}
+
+impl From<&BleError> for RecordedError {
+    fn from(e: &BleError) -> Self {
+        use btleplug::Error as Btleplug;
+        match e {
+            BleError::Btleplug(Btleplug::PermissionDenied) => Self::PermissionDenied,
+            BleError::Btleplug(Btleplug::DeviceNotFound) => Self::DeviceNotFound,
+            BleError::Btleplug(Btleplug::NotConnected) => Self::NotConnected,
+            BleError::Btleplug(Btleplug::NotSupported(s)) => Self::NotSupported(s.clone()),
+            BleError::Btleplug(Btleplug::TimedOut(d)) => Self::TimedOut(*d),
+            BleError::Btleplug(Btleplug::RuntimeError(s)) => Self::RuntimeError(s.clone()),
+            BleError::RuntimeNotInitialized => Self::RuntimeNotInitialized,
+            BleError::UnknownPeripheral(id) => Self::UnknownPeripheral(id.clone()),
+            BleError::CharacNotFound(uuid) => Self::CharacNotFound(*uuid),
+            BleError::CharacNotAvailable(s) => Self::CharacNotAvailable(s.clone()),
+            BleError::NoDeviceConnected => Self::NoDeviceConnected,
+            BleError::ServiceNotFound => Self::ServiceNotFound,
+            BleError::AlreadyConnected => Self::AlreadyConnected,
+            BleError::HandlerNotInitialized => Self::HandlerNotInitialized,
+            BleError::NoMatchingDevice => Self::NoMatchingDevice,
+            BleError::NoAdapters => Self::NoAdapters,
+            BleError::AdapterOff => Self::AdapterOff,
+            BleError::AdapterNotFound(s) => Self::AdapterNotFound(s.clone()),
+            BleError::InvalidAddress(e) => Self::InvalidAddress(*e),
+            BleError::JniSetup(s) => Self::JniSetup(s.clone()),
+            BleError::BlockingInAsyncContext => Self::BlockingInAsyncContext,
+            BleError::HandlerAlreadyInitialized => Self::HandlerAlreadyInitialized,
+            BleError::SendingDevices => Self::SendingDevices,
+            BleError::SinkClosed => Self::SinkClosed,
+            e => Self::Other(e.to_string()),
+        }
+    }
+}
+
+impl From<RecordedError> for BleError {
+    fn from(e: RecordedError) -> Self {
+        use btleplug::Error as Btleplug;
+        match e {
+            RecordedError::PermissionDenied => Btleplug::PermissionDenied.into(),
+            RecordedError::DeviceNotFound => Btleplug::DeviceNotFound.into(),
+            RecordedError::NotConnected => Btleplug::NotConnected.into(),
+            RecordedError::NotSupported(s) => Btleplug::NotSupported(s).into(),
+            RecordedError::TimedOut(d) => Btleplug::TimedOut(d).into(),
+            RecordedError::RuntimeError(s) => Btleplug::RuntimeError(s).into(),
+            RecordedError::RuntimeNotInitialized => Self::RuntimeNotInitialized,
+            RecordedError::UnknownPeripheral(id) => Self::UnknownPeripheral(id),
+            RecordedError::CharacNotFound(uuid) => Self::CharacNotFound(uuid),
+            RecordedError::CharacNotAvailable(s) => Self::CharacNotAvailable(s),
+            RecordedError::NoDeviceConnected => Self::NoDeviceConnected,
+            RecordedError::ServiceNotFound => Self::ServiceNotFound,
+            RecordedError::AlreadyConnected => Self::AlreadyConnected,
+            RecordedError::HandlerNotInitialized => Self::HandlerNotInitialized,
+            RecordedError::NoMatchingDevice => Self::NoMatchingDevice,
+            RecordedError::NoAdapters => Self::NoAdapters,
+            RecordedError::AdapterOff => Self::AdapterOff,
+            RecordedError::AdapterNotFound(s) => Self::AdapterNotFound(s),
+            RecordedError::InvalidAddress(e) => Self::InvalidAddress(e),
+            RecordedError::JniSetup(s) => Self::JniSetup(s),
+            RecordedError::BlockingInAsyncContext => Self::BlockingInAsyncContext,
+            RecordedError::HandlerAlreadyInitialized => Self::HandlerAlreadyInitialized,
+            RecordedError::SendingDevices => Self::SendingDevices,
+            RecordedError::SinkClosed => Self::SinkClosed,
+            RecordedError::Other(message) => Self::Replayed(message),
+        }
+    }
+}
+
+impl RecordedOp {
+    /// Operations not triggered by the application, they are delivered during a replay instead of being served
+    fn is_passive(&self) -> bool {
+        match self {
+            Self::Notification { .. } => true,
+            Self::Disconnected { reason } => *reason != DisconnectReason::UserInitiated,
+            _ => false,
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            Self::Discover { .. } => "discover",
+            Self::Connect { .. } => "connect",
+            Self::Services { .. } => "services",
+            Self::Read { .. } => "read",
+            Self::Write { .. } => "write",
+            Self::Subscribe { .. } => "subscribe",
+            Self::Notification { .. } => "notification",
+            Self::Disconnected { .. } => "disconnect",
+        }
+    }
+}
+
+impl Recording {
+    pub fn to_json(&self) -> Result<String, BleError> {
+        serde_json::to_string_pretty(self).map_err(invalid)
+    }
+
+    pub fn from_json(json: &str) -> Result<Self, BleError> {
+        serde_json::from_str::<Self>(json)
+            .map_err(invalid)?
+            .checked()
+    }
+
+    pub fn to_cbor(&self) -> Result<Vec<u8>, BleError> {
+        let mut data = vec![];
+        ciborium::into_writer(self, &mut data).map_err(invalid)?;
+        Ok(data)
+    }
+
+    pub fn from_cbor(data: &[u8]) -> Result<Self, BleError> {
+        ciborium::from_reader::<Self, _>(data)
+            .map_err(invalid)?
+            .checked()
+    }
+
+    /// Writes the recording as CBOR if the file ends with `.cbor`, as JSON otherwise
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), BleError> {
+        let path = path.as_ref();
+        let data = match is_cbor(path) {
+            true => self.to_cbor()?,
+            false => self.to_json()?.into_bytes(),
+        };
+        std::fs::write(path, data).map_err(invalid)
+    }
+
+    /// Reads a recording written by [save](Recording::save)
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, BleError> {
+        let path = path.as_ref();
+        let data = std::fs::read(path).map_err(invalid)?;
+        match is_cbor(path) {
+            true => Self::from_cbor(&data),
+            false => Self::from_json(&String::from_utf8(data).map_err(invalid)?),
+        }
+    }
+
+    fn checked(self) -> Result<Self, BleError> {
+        if self.version != RECORDING_VERSION {
+            return Err(BleError::InvalidRecording(format!(
+                "unsupported version {}, expected {RECORDING_VERSION}",
+                self.version
+            )));
+        }
+        Ok(self)
+    }
+}
+
+fn is_cbor(path: &Path) -> bool {
+    path.extension().is_some_and(|ext| ext == "cbor")
+}
+
+fn invalid(e: impl ToString) -> BleError {
+    BleError::InvalidRecording(e.to_string())
+}
+
+struct Recorder {
+    start: Instant,
+    entries: Vec<RecordEntry>,
+}
+
+static RECORDER: Mutex<Option<Recorder>> = Mutex::new(None);
+
+/// Starts recording all operations, a running recording is discarded
+pub fn start_recording() {
+    *RECORDER.lock().unwrap_or_else(|e| e.into_inner()) = Some(Recorder {
+        start: Instant::now(),
+        entries: vec![],
+    });
+}
+
+/// Stops recording and returns the recorded session, `None` if nothing was being recorded
+pub fn stop_recording() -> Option<Recording> {
+    let recorder = RECORDER.lock().unwrap_or_else(|e| e.into_inner()).take()?;
+    Some(Recording {
+        version: RECORDING_VERSION,
+        entries: recorder.entries,
+    })
+}
+
+pub fn is_recording() -> bool {
+    RECORDER.lock().unwrap_or_else(|e| e.into_inner()).is_some()
+}
+
+/// Adds the operation to the running recording, `op` is only evaluated while recording
+pub(crate) fn record(op: impl FnOnce() -> RecordedOp) {
+    if let Some(recorder) = RECORDER.lock().unwrap_or_else(|e| e.into_inner()).as_mut() {
+        recorder.entries.push(RecordEntry {
+            at: recorder.start.elapsed().as_millis() as u64,
+            op: op(),
+        });
+    }
+}
+
+pub(crate) fn recorded<T: Clone>(result: &Result<T, BleError>) -> RecordedResult<T> {
+    result.as_ref().cloned().map_err(RecordedError::from)
+}
+
+pub(crate) fn recorded_devices(
+    result: &Result<Vec<BleDevice>, BleError>,
+) -> RecordedResult<Vec<RecordedDevice>> {
+    match result {
+        Ok(devices) => Ok(devices.iter().map(RecordedDevice::from).collect()),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Configuration passed to [start_replay]
+#[derive(Debug, Clone)]
+pub struct ReplayConfig {
+    /// Fail with [BleError::ReplayDiverged] if written data or connect targets differ from the recording.
+    /// The order of operations and the characteristics used always have to match.
+    pub strict: bool,
+    /// Deliver notifications and disconnects with the recorded delays instead of immediately
+    pub timing: bool,
+}
+
+impl Default for ReplayConfig {
+    fn default() -> Self {
+        Self {
+            strict: true,
+            timing: true,
+        }
+    }
+}
+
+static REPLAYER: RwLock<Option<Arc<Replayer>>> = RwLock::new(None);
+
+/// Serves the blec API from the recording until [stop_replay] is called.
+/// A running replay is replaced.
+pub fn start_replay(recording: Recording, config: ReplayConfig) -> Result<(), BleError> {
+    let recording = recording.checked()?;
+    if RUNTIME.get().is_none() {
+        setup::create_runtime(RuntimeConfig::default())?;
+    }
+    let rt = RUNTIME.get().ok_or(BleError::RuntimeNotInitialized)?;
+    let replayer = Arc::new(Replayer {
+        entries: recording.entries,
+        config,
+        state: Mutex::new(ReplayState {
+            position: 0,
+            advanced_at: tokio::time::Instant::now(),
+            connected: false,
+            device: None,
+            listeners: vec![],
+            on_disconnect: None,
+        }),
+        advanced: Notify::new(),
+        emitter: Mutex::new(None),
+    });
+    let emitter = rt.spawn(replayer.clone().emit_passive());
+    *replayer.emitter.lock().unwrap_or_else(|e| e.into_inner()) = Some(emitter.abort_handle());
+    if let Some(old) = REPLAYER
+        .write()
+        .unwrap_or_else(|e| e.into_inner())
+        .replace(replayer)
+    {
+        old.abort();
+    }
+    Ok(())
+}
+
+/// Stops the replay. In strict mode it fails with [BleError::ReplayDiverged]
+/// if operations of the recording were not replayed.
+pub fn stop_replay() -> Result<(), BleError> {
+    let Some(replayer) = REPLAYER.write().unwrap_or_else(|e| e.into_inner()).take() else {
+        return Ok(());
+    };
+    replayer.abort();
+    let position = replayer.lock().position;
+    let missing = replayer.entries[position..]
+        .iter()
+        .filter(|e| !e.op.is_passive())
+        .count();
+    if replayer.config.strict && missing > 0 {
+        return Err(BleError::ReplayDiverged(format!(
+            "{missing} recorded operations were not replayed"
+        )));
+    }
+    Ok(())
+}
+
+pub(crate) fn replayer() -> Option<Arc<Replayer>> {
+    REPLAYER.read().unwrap_or_else(|e| e.into_inner()).clone()
+}
+
+struct ReplayState {
+    /// index of the next entry to replay
+    position: usize,
+    /// when the previous entry was replayed
+    advanced_at: tokio::time::Instant,
+    connected: bool,
+    device: Option<BleDevice>,
+    listeners: Vec<(Uuid, NotifyCallback)>,
+    on_disconnect: Option<DisconnectCallback>,
+}
+
+pub(crate) struct Replayer {
+    entries: Vec<RecordEntry>,
+    config: ReplayConfig,
+    state: Mutex<ReplayState>,
+    /// notified whenever an entry was replayed
+    advanced: Notify,
+    emitter: Mutex<Option<AbortHandle>>,
+}
+
+impl Replayer {
+    fn lock(&self) -> MutexGuard<'_, ReplayState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn abort(&self) {
+        if let Some(emitter) = self
+            .emitter
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .take()
+        {
+            emitter.abort();
+        }
+    }
+
+    fn advance(&self, state: &mut ReplayState) {
+        state.position += 1;
+        state.advanced_at = tokio::time::Instant::now();
+        self.advanced.notify_waiters();
+    }
+
+    /// Waits until the notifications and disconnects preceding the next operation were delivered
+    async fn settle(&self) -> MutexGuard<'_, ReplayState> {
+        loop {
+            // created before checking so a concurrent advance is not missed
+            let advanced = self.advanced.notified();
+            {
+                let state = self.lock();
+                if !self
+                    .entries
+                    .get(state.position)
+                    .is_some_and(|e| e.op.is_passive())
+                {
+                    return state;
+                }
+            }
+            advanced.await;
+        }
+    }
+
+    /// Takes the next recorded operation, it has to be of the same kind as `expected`
+    async fn next(&self, expected: &'static str) -> Result<RecordedOp, BleError> {
+        let (mut state, op) = self.peek(expected).await?;
+        self.advance(&mut state);
+        Ok(op)
+    }
+
+    /// Like [next](Self::next) but doesn't advance, the lock is held until the caller does
+    async fn peek(
+        &self,
+        expected: &'static str,
+    ) -> Result<(MutexGuard<'_, ReplayState>, RecordedOp), BleError> {
+        let state = self.settle().await;
+        let op = match self.entries.get(state.position) {
+            None => {
+                return Err(BleError::ReplayDiverged(format!(
+                    "{expected} after the end of the recording"
+                )))
+            }
+            Some(entry) if entry.op.name() != expected => {
+                return Err(BleError::ReplayDiverged(format!(
+                    "{expected} instead of the recorded {}",
+                    entry.op.name()
+                )))
+            }
+            Some(entry) => entry.op.clone(),
+        };
+        Ok((state, op))
+    }
+
+    /// Delivers notifications and disconnects once the operations before them were replayed
+    async fn emit_passive(self: Arc<Self>) {
+        loop {
+            let advanced = self.advanced.notified();
+            let due = {
+                let state = self.lock();
+                match self.entries.get(state.position) {
+                    None => return,
+                    Some(entry) if entry.op.is_passive() => {
+                        Some(state.advanced_at + self.delay(state.position))
+                    }
+                    Some(_) => None,
+                }
+            };
+            match due {
+                Some(due) => {
+                    tokio::time::sleep_until(due).await;
+                    self.emit_next();
+                }
+                None => advanced.await,
+            }
+        }
+    }
+
+    fn delay(&self, position: usize) -> Duration {
+        if !self.config.timing {
+            return Duration::ZERO;
+        }
+        let previous = match position {
+            0 => 0,
+            _ => self.entries[position - 1].at,
+        };
+        Duration::from_millis(self.entries[position].at.saturating_sub(previous))
+    }
+
+    fn emit_next(&self) {
+        let mut state = self.lock();
+        let Some(entry) = self.entries.get(state.position) else {
+            return;
+        };
+        match &entry.op {
+            RecordedOp::Notification { charac, data } => {
+                for (_, cb) in state.listeners.iter().filter(|(uuid, _)| uuid == charac) {
+                    let (cb, data) = (cb.clone(), data.clone());
+                    tokio::task::spawn_blocking(move || cb(&data));
+                }
+            }
+            RecordedOp::Disconnected { reason } => Self::disconnected(&mut state, *reason),
+            _ => return,
+        }
+        self.advance(&mut state);
+    }
+
+    fn disconnected(state: &mut ReplayState, reason: DisconnectReason) {
+        state.connected = false;
+        state.listeners.clear();
+        let device = state.device.take();
+        if let (Some(callback), Some(device)) = (state.on_disconnect.take(), device) {
+            let device = BleDevice {
+                is_connected: false,
+                ..device
+            };
+            tokio::task::spawn_blocking(move || callback(device, reason));
+        }
+    }
+
+    pub(crate) async fn discover(&self) -> Result<Vec<BleDevice>, BleError> {
+        match self.next("discover").await? {
+            RecordedOp::Discover { result } => {
+                Ok(replayed(result)?.into_iter().map(BleDevice::from).collect())
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    /// Replays a connect, `target` is `None` for devices selected by a filter
+    pub(crate) async fn connect(
+        &self,
+        target: Option<ConnectTarget>,
+        on_disconnect: Option<DisconnectCallback>,
+    ) -> Result<Option<BleDevice>, BleError> {
+        let RecordedOp::Connect {
+            target: recorded,
+            result,
+            device,
+        } = self.next("connect").await?
+        else {
+            unreachable!()
+        };
+        let target = target.as_ref().map(RecordedTarget::from);
+        if self.config.strict && target != recorded {
+            return Err(BleError::ReplayDiverged(format!(
+                "connect to {target:?} instead of the recorded {recorded:?}"
+            )));
+        }
+        replayed(result)?;
+        let device = device.map(BleDevice::from);
+        let mut state = self.lock();
+        // like the handler, a new connection ends the previous one
+        if state.connected {
+            Self::disconnected(&mut state, DisconnectReason::UserInitiated);
+        }
+        state.connected = true;
+        state.device = device.clone();
+        state.on_disconnect = on_disconnect;
+        Ok(device)
+    }
+
+    pub(crate) async fn disconnect(&self) -> Result<(), BleError> {
+        // disconnecting without a connection is not recorded
+        if !self.settle().await.connected {
+            return Ok(());
+        }
+        self.next("disconnect").await?;
+        Self::disconnected(&mut self.lock(), DisconnectReason::UserInitiated);
+        Ok(())
+    }
+
+    pub(crate) async fn services(&self) -> Result<Vec<BleService>, BleError> {
+        match self.next("services").await? {
+            RecordedOp::Services { result } => replayed(result),
+            _ => unreachable!(),
+        }
+    }
+
+    pub(crate) async fn recv_data(&self, charac: Uuid) -> Result<Vec<u8>, BleError> {
+        let RecordedOp::Read {
+            charac: recorded,
+            result,
+        } = self.next("read").await?
+        else {
+            unreachable!()
+        };
+        check_charac(charac, recorded)?;
+        replayed(result)
+    }
+
+    pub(crate) async fn send_data(&self, charac: Uuid, data: Vec<u8>) -> Result<(), BleError> {
+        let RecordedOp::Write {
+            charac: recorded,
+            data: recorded_data,
+            result,
+        } = self.next("write").await?
+        else {
+            unreachable!()
+        };
+        check_charac(charac, recorded)?;
+        if self.config.strict && data != recorded_data {
+            return Err(BleError::ReplayDiverged(format!(
+                "wrote {data:02x?} to {charac} instead of the recorded {recorded_data:02x?}"
+            )));
+        }
+        replayed(result)
+    }
+
+    pub(crate) async fn subscribe(
+        &self,
+        charac: Uuid,
+        callback: impl Fn(&[u8]) + Send + Sync + 'static,
+    ) -> Result<(), BleError> {
+        let (mut state, op) = self.peek("subscribe").await?;
+        let RecordedOp::Subscribe {
+            charac: recorded,
+            result,
+        } = op
+        else {
+            unreachable!()
+        };
+        let result = check_charac(charac, recorded).and_then(|_| replayed(result));
+        // registered before advancing so the following notifications aren't missed
+        if result.is_ok() {
+            state.listeners.push((charac, Arc::new(callback)));
+        }
+        self.advance(&mut state);
+        result
+    }
+
+    #[cfg(feature = "derive")]
+    pub(crate) fn set_on_disconnect(
+        &self,
+        on_disconnect: DisconnectCallback,
+    ) -> Result<(), BleError> {
+        let mut state = self.lock();
+        if !state.connected {
+            return Err(BleError::NoDeviceConnected);
+        }
+        state.on_disconnect = Some(on_disconnect);
+        Ok(())
+    }
+
+    pub(crate) fn is_connected(&self) -> bool {
+        self.lock().connected
+    }
+
+    pub(crate) fn connected_device(&self) -> Result<BleDevice, BleError> {
+        let state = self.lock();
+        match (&state.device, state.connected) {
+            (Some(device), true) => Ok(device.clone()),
+            _ => Err(BleError::NoDeviceConnected),
+        }
+    }
+
+    /// Looks up the id among all devices of the recording
+    pub(crate) fn device_id(&self, id: &str) -> Result<BleDeviceId, BleError> {
+        self.entries
+            .iter()
+            .flat_map(|entry| match &entry.op {
+                RecordedOp::Discover {
+                    result: Ok(devices),
+                } => devices.iter().collect(),
+                RecordedOp::Connect {
+                    device: Some(device),
+                    ..
+                } => vec![device],
+                _ => vec![],
+            })
+            .find(|device| device.id == id)
+            .map(|device| BleDeviceId::recorded(device.id.clone()))
+            .ok_or(BleError::UnknownPeripheral(id.to_string()))
+    }
+}
+
+fn check_charac(charac: Uuid, recorded: Uuid) -> Result<(), BleError> {
+    if charac != recorded {
+        return Err(BleError::ReplayDiverged(format!(
+            "used {charac} instead of the recorded {recorded}"
+        )));
+    }
+    Ok(())
+}
+
+fn replayed<T>(result: RecordedResult<T>) -> Result<T, BleError> {
+    result.map_err(BleError::from)
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::blocking;
+    use std::sync::mpsc;
+
+    const SERVICE: Uuid = Uuid::from_u128(0x180f);
+    const CHARAC: Uuid = Uuid::from_u128(0x2a19);
+
+    /// the recorder and the replayer are global, all tests using them hold this lock
+    pub(crate) static SERIAL: Mutex<()> = Mutex::new(());
+
+    fn device() -> RecordedDevice {
+        RecordedDevice {
+            id: "/org/bluez/hci0/dev_AA_BB_CC_DD_EE_FF".to_string(),
+            address: "AA:BB:CC:DD:EE:FF".parse().unwrap(),
+            address_type: Some(BleAddressType::Random),
+            name: "Battery".to_string(),
+            is_connected: false,
+            rssi: Some(-60),
+            services: vec![SERVICE],
+            manufacturer_data: HashMap::from([(0x004c, vec![1, 2])]),
+            service_data: HashMap::new(),
+        }
+    }
+
+    fn session() -> Recording {
+        let device = device();
+        start_recording();
+        record(|| RecordedOp::Discover {
+            result: Ok(vec![device.clone()]),
+        });
+        record(|| RecordedOp::Connect {
+            target: Some(RecordedTarget::Id(device.id.clone())),
+            result: Ok(()),
+            device: Some(RecordedDevice {
+                is_connected: true,
+                ..device
+            }),
+        });
+        record(|| RecordedOp::Subscribe {
+            charac: CHARAC,
+            result: Ok(()),
+        });
+        record(|| RecordedOp::Notification {
+            charac: CHARAC,
+            data: vec![42],
+        });
+        record(|| RecordedOp::Write {
+            charac: CHARAC,
+            data: vec![1],
+            result: Ok(()),
+        });
+        record(|| RecordedOp::Read {
+            charac: CHARAC,
+            result: Err((&BleError::CharacNotFound(CHARAC)).into()),
+        });
+        record(|| RecordedOp::Disconnected {
+            reason: DisconnectReason::UserInitiated,
+        });
+        stop_recording().unwrap()
+    }
+
+    pub(crate) fn no_timing() -> ReplayConfig {
+        ReplayConfig {
+            strict: true,
+            timing: false,
+        }
+    }
+
+    fn replay_session() {
+        let devices = blocking::discover(1000).unwrap();
+        assert_eq!(devices.len(), 1);
+        assert_eq!(devices[0].address, device().address);
+        let id = blocking::device_id(device().id).unwrap();
+        assert_eq!(devices[0].id, id);
+
+        let (disconnected_tx, disconnected) = mpsc::channel();
+        blocking::connect(
+            id,
+            SERVICE,
+            vec![CHARAC],
+            Some(move |device: BleDevice, reason| {
+                let _ = disconnected_tx.send((device.name, reason));
+            }),
+        )
+        .unwrap();
+        assert!(blocking::connected_device().unwrap().is_connected);
+
+        let (notified_tx, notified) = mpsc::channel();
+        blocking::subscribe(CHARAC, move |data: &[u8]| {
+            let _ = notified_tx.send(data.to_vec());
+        })
+        .unwrap();
+        let timeout = Duration::from_secs(5);
+        assert_eq!(notified.recv_timeout(timeout).unwrap(), vec![42]);
+
+        blocking::send_data(CHARAC, vec![1]).unwrap();
+        assert!(matches!(
+            blocking::recv_data(CHARAC),
+            Err(BleError::CharacNotFound(CHARAC))
+        ));
+        blocking::disconnect().unwrap();
+        assert_eq!(
+            disconnected.recv_timeout(timeout).unwrap(),
+            ("Battery".to_string(), DisconnectReason::UserInitiated)
+        );
+        assert!(!blocking::is_connected().unwrap());
+    }
+
+    #[test]
+    fn replays_saved_recordings() {
+        let _serial = SERIAL.lock().unwrap_or_else(|e| e.into_inner());
+        let recording = session();
+        for extension in ["json", "cbor"] {
+            let path = std::env::temp_dir()
+                .join(format!("blec-record-{}.{extension}", std::process::id()));
+            recording.save(&path).unwrap();
+            let loaded = Recording::load(&path);
+            let _ = std::fs::remove_file(&path);
+            let loaded = loaded.unwrap();
+            assert_eq!(loaded, recording, "{extension}");
+
+            start_replay(loaded, no_timing()).unwrap();
+            replay_session();
+            stop_replay().unwrap();
+        }
+    }
+
+    #[test]
+    fn strict_replay_fails_on_divergence() {
+        let _serial = SERIAL.lock().unwrap_or_else(|e| e.into_inner());
+        let other: BleAddress = "11:22:33:44:55:66".parse().unwrap();
+        start_replay(session(), no_timing()).unwrap();
+        blocking::discover(1000).unwrap();
+        assert!(matches!(
+            blocking::connect(other, SERVICE, vec![], None::<fn(_, _)>),
+            Err(BleError::ReplayDiverged(_))
+        ));
+        // skipping the remaining operations diverges as well
+        assert!(matches!(stop_replay(), Err(BleError::ReplayDiverged(_))));
+
+        start_replay(
+            session(),
+            ReplayConfig {
+                strict: false,
+                timing: false,
+            },
+        )
+        .unwrap();
+        blocking::discover(1000).unwrap();
+        blocking::connect(other, SERVICE, vec![], None::<fn(_, _)>).unwrap();
+        stop_replay().unwrap();
+    }
+
+    #[test]
+    fn rebuilds_errors() {
+        let errors = [
+            BleError::Btleplug(btleplug::Error::DeviceNotFound),
+            BleError::CharacNotFound(CHARAC),
+            BleError::InvalidAddress(ParseBleAddressError::InvalidLength(3)),
+            BleError::AdapterNotFound("hci1".to_string()),
+            BleError::SinkClosed,
+        ];
+        for e in errors {
+            let rebuilt = BleError::from(RecordedError::from(&e));
+            assert_eq!(std::mem::discriminant(&rebuilt), std::mem::discriminant(&e));
+            assert_eq!(rebuilt.to_string(), e.to_string());
+        }
+        let other = btleplug::Error::Other("gatt failure".into());
+        let rebuilt = BleError::from(RecordedError::from(&BleError::Btleplug(other)));
+        assert!(matches!(&rebuilt, BleError::Replayed(_)));
+        assert_eq!(rebuilt.to_string(), "Btleplug error: gatt failure");
+    }
+}
@@ -10,17 +10,154 @@ mod ios;
 #[cfg(target_os = "ios")]
 pub use ios::*;
 
+use futures::Future;
 use once_cell::sync::OnceCell;
-use tokio::runtime::Runtime;
-pub static RUNTIME: OnceCell<Runtime> = once_cell::sync::OnceCell::new();
+use tokio::runtime::{Builder, Handle, Runtime, RuntimeFlavor};
+use tokio::task::JoinHandle;
+pub static RUNTIME: OnceCell<BleRuntime> = once_cell::sync::OnceCell::new();
 
-#[cfg(not(any(target_os = "android", target_os = "ios")))]
-pub fn create_runtime() -> Result<(), super::BleError> {
-    let runtime = tokio::runtime::Builder::new_current_thread()
-        .enable_all()
-        .thread_name("BLE Thread")
-        .build()
-        .unwrap();
+/// Runtime blec runs its tasks on, configured with [InitConfig](crate::InitConfig)
+#[derive(Debug, Clone)]
+pub enum RuntimeConfig {
+    /// single threaded runtime owned by blec
+    CurrentThread { thread_name: String },
+    /// multi threaded runtime owned by blec, uses one worker per core if `worker_threads` is `None`
+    MultiThread {
+        worker_threads: Option<usize>,
+        thread_name: String,
+    },
+    /// existing runtime of the application.
+    /// On Android its threads have to call [on_thread_start](crate::on_thread_start)
+    /// and [on_thread_stop](crate::on_thread_stop) to be attached to the JVM.
+    Handle(Handle),
+}
+
+impl Default for RuntimeConfig {
+    fn default() -> Self {
+        Self::CurrentThread {
+            thread_name: "BLE Thread".into(),
+        }
+    }
+}
+
+pub enum BleRuntime {
+    Owned(Runtime),
+    Shared(Handle),
+}
+
+impl BleRuntime {
+    pub fn handle(&self) -> &Handle {
+        match self {
+            Self::Owned(rt) => rt.handle(),
+            Self::Shared(handle) => handle,
+        }
+    }
+
+    pub fn spawn<F>(&self, f: F) -> JoinHandle<F::Output>
+    where
+        F: Future + Send + 'static,
+        F::Output: Send + 'static,
+    {
+        self.handle().spawn(f)
+    }
+
+    pub fn spawn_blocking<F, R>(&self, f: F) -> JoinHandle<R>
+    where
+        F: FnOnce() -> R + Send + 'static,
+        R: Send + 'static,
+    {
+        self.handle().spawn_blocking(f)
+    }
+
+    /// Blocking inside of an async context panics,
+    /// except for a shared runtime blocked from a multi threaded runtime where `block_in_place` is used
+    pub fn can_block(&self) -> bool {
+        match Handle::try_current() {
+            Err(_) => true,
+            Ok(current) => {
+                matches!(self, Self::Shared(_))
+                    && current.runtime_flavor() == RuntimeFlavor::MultiThread
+            }
+        }
+    }
+
+    pub fn block_on<F: Future>(&self, f: F) -> F::Output {
+        match self {
+            Self::Owned(rt) => rt.block_on(f),
+            // a shared multi threaded runtime may be blocked from one of its own workers
+            Self::Shared(handle) => match Handle::try_current() {
+                Ok(current) if current.runtime_flavor() == RuntimeFlavor::MultiThread => {
+                    tokio::task::block_in_place(|| handle.block_on(f))
+                }
+                _ => handle.block_on(f),
+            },
+        }
+    }
+}
+
+pub fn create_runtime(config: RuntimeConfig) -> Result<(), super::BleError> {
+    setup_platform()?;
+    let runtime = match config {
+        RuntimeConfig::Handle(handle) => BleRuntime::Shared(handle),
+        RuntimeConfig::CurrentThread { thread_name } => {
+            let mut builder = Builder::new_current_thread();
+            builder.thread_name(&thread_name);
+            let runtime = build_runtime(builder)?;
+            RUNTIME
+                .set(BleRuntime::Owned(runtime))
+                .map_err(|_| super::BleError::Runtime)?;
+            return drive_runtime(thread_name);
+        }
+        RuntimeConfig::MultiThread {
+            worker_threads,
+            thread_name,
+        } => {
+            let mut builder = Builder::new_multi_thread();
+            if let Some(worker_threads) = worker_threads {
+                builder.worker_threads(worker_threads);
+            }
+            builder.thread_name(thread_name);
+            BleRuntime::Owned(build_runtime(builder)?)
+        }
+    };
     RUNTIME.set(runtime).map_err(|_| super::BleError::Runtime)?;
     Ok(())
 }
+
+/// Runs the tasks of the current thread runtime on its own thread,
+/// otherwise they would only make progress while blocking on the runtime.
+fn drive_runtime(thread_name: String) -> Result<(), super::BleError> {
+    std::thread::Builder::new()
+        .name(thread_name)
+        .spawn(|| {
+            #[cfg(target_os = "android")]
+            on_thread_start();
+            if let Some(BleRuntime::Owned(rt)) = RUNTIME.get() {
+                rt.block_on(futures::future::pending::<()>());
+            }
+        })
+        .map_err(|_| super::BleError::Runtime)?;
+    Ok(())
+}
+
+fn build_runtime(mut builder: Builder) -> Result<Runtime, super::BleError> {
+    builder.enable_all();
+    configure_runtime(&mut builder);
+    builder.build().map_err(|_| super::BleError::Runtime)
+}
+
+#[cfg(not(target_os = "android"))]
+pub fn check_setup_error() -> Result<(), super::BleError> {
+    Ok(())
+}
+
+#[cfg(not(target_os = "android"))]
+pub fn clear_setup_error() {}
+
+#[cfg(not(any(target_os = "android", target_os = "ios")))]
+fn setup_platform() -> Result<(), super::BleError> {
+    Ok(())
+}
+
+#[cfg(not(any(target_os = "android", target_os = "ios")))]
+fn configure_runtime(_builder: &mut Builder) {}
@@ -4,62 +4,87 @@ use jni::objects::GlobalRef;
 use jni::{AttachGuard, JNIEnv, JavaVM};
 use once_cell::sync::OnceCell;
 use std::cell::RefCell;
+use std::sync::Mutex;
+use std::thread::{self, ThreadId};
+use tokio::runtime::Builder;
 
-use super::RUNTIME;
 use crate::BleError;
 
 static CLASS_LOADER: OnceCell<GlobalRef> = OnceCell::new();
 pub static JAVAVM: OnceCell<JavaVM> = OnceCell::new();
+/// error while loading the library, reported by all following calls
+static LOAD_ERROR: OnceCell<String> = OnceCell::new();
+/// error while attaching a thread, reported until the thread is attached or blec is deinitialized
+static SETUP_ERROR: Mutex<Option<(ThreadId, String)>> = Mutex::new(None);
 
 std::thread_local! {
-    static JNI_ENV: RefCell<Option<AttachGuard<'static>>> = RefCell::new(None);
+    static JNI_ENV: RefCell<Option<AttachGuard<'static>>> = const { RefCell::new(None) };
 }
 
-pub fn create_runtime() -> Result<(), BleError> {
+pub fn setup_platform() -> Result<(), BleError> {
     let vm = JAVAVM.get().ok_or(BleError::JavaVM)?;
-    let env = vm.attach_current_thread().unwrap();
-
-    setup_class_loader(&env)?;
-    let runtime = tokio::runtime::Builder::new_current_thread()
-        .enable_all()
-        .thread_name("BLE Thread")
-        .on_thread_stop(move || {
-            JNI_ENV.with(|f| *f.borrow_mut() = None);
-        })
-        .on_thread_start(move || {
-            // We now need to call the following code block via JNI calls. God help us.
-            //
-            //  java.lang.Thread.currentThread().setContextClassLoader(
-            //    java.lang.ClassLoader.getSystemClassLoader()
-            //  );
-            let vm = JAVAVM.get().unwrap();
-            let env = vm.attach_current_thread().unwrap();
-
-            let thread = env
-                .call_static_method(
-                    "java/lang/Thread",
-                    "currentThread",
-                    "()Ljava/lang/Thread;",
-                    &[],
-                )
-                .unwrap()
-                .l()
-                .unwrap();
-            env.call_method(
-                thread,
-                "setContextClassLoader",
-                "(Ljava/lang/ClassLoader;)V",
-                &[CLASS_LOADER.get().unwrap().as_obj().into()],
-            )
-            .unwrap();
-            JNI_ENV.with(|f| *f.borrow_mut() = Some(env));
-        })
-        .build()
-        .unwrap();
-    RUNTIME.set(runtime).map_err(|_| BleError::Runtime)?;
+    let env = vm.attach_current_thread()?;
+
+    setup_class_loader(&env)
+}
+
+pub fn configure_runtime(builder: &mut Builder) {
+    builder
+        .on_thread_stop(on_thread_stop)
+        .on_thread_start(on_thread_start);
+}
+
+/// Attaches the current thread to the JVM, has to run on every thread of a runtime
+/// passed with [RuntimeConfig::Handle](crate::RuntimeConfig::Handle)
+pub fn on_thread_start() {
+    let result = attach_thread();
+    let mut error = SETUP_ERROR.lock().unwrap_or_else(|e| e.into_inner());
+    match result {
+        Ok(()) => {
+            if error
+                .as_ref()
+                .is_some_and(|(id, _)| *id == thread::current().id())
+            {
+                *error = None;
+            }
+        }
+        Err(e) => *error = Some((thread::current().id(), e.to_string())),
+    }
+}
+
+fn attach_thread() -> Result<(), BleError> {
+    // We now need to call the following code block via JNI calls. God help us.
+    //
+    //  java.lang.Thread.currentThread().setContextClassLoader(
+    //    java.lang.ClassLoader.getSystemClassLoader()
+    //  );
+    let vm = JAVAVM.get().ok_or(BleError::JavaVM)?;
+    let env = vm.attach_current_thread()?;
+
+    let thread = env
+        .call_static_method(
+            "java/lang/Thread",
+            "currentThread",
+            "()Ljava/lang/Thread;",
+            &[],
+        )?
+        .l()?;
+    let class_loader = CLASS_LOADER.get().ok_or(BleError::ClassLoader)?;
+    env.call_method(
+        thread,
+        "setContextClassLoader",
+        "(Ljava/lang/ClassLoader;)V",
+        &[class_loader.as_obj().into()],
+    )?;
+    JNI_ENV.with(|f| *f.borrow_mut() = Some(env));
     Ok(())
 }
 
+/// Detaches the current thread from the JVM
+pub fn on_thread_stop() {
+    JNI_ENV.with(|f| *f.borrow_mut() = None);
+}
+
 fn setup_class_loader(env: &JNIEnv) -> Result<(), BleError> {
     let thread = env
         .call_static_method(
@@ -83,12 +108,41 @@ fn setup_class_loader(env: &JNIEnv) -> Result<(), BleError> {
         .map_err(|_| BleError::ClassLoader)
 }
 
+/// Returns the error that happened while loading the library or the last one attaching a thread.
+/// A load error is kept, as blec can't work in a broken JNI state.
+pub fn check_setup_error() -> Result<(), BleError> {
+    if let Some(e) = LOAD_ERROR.get() {
+        return Err(BleError::JniSetup(e.clone()));
+    }
+    match SETUP_ERROR
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .as_ref()
+    {
+        Some((_, e)) => Err(BleError::JniSetup(e.clone())),
+        None => Ok(()),
+    }
+}
+
+/// Forgets the thread attach error, called by [deinit](crate::deinit)
+pub fn clear_setup_error() {
+    *SETUP_ERROR.lock().unwrap_or_else(|e| e.into_inner()) = None;
+}
+
+fn init_jni(vm: &JavaVM) -> Result<(), BleError> {
+    let env = vm.get_env()?;
+    jni_utils::init(&env)?;
+    btleplug::platform::init(&env)?;
+    Ok(())
+}
+
 #[no_mangle]
 pub extern "C" fn JNI_OnLoad(vm: jni::JavaVM, res: *const std::os::raw::c_void) -> jni::sys::jint {
     let _res = res;
-    let env = vm.get_env().unwrap();
-    jni_utils::init(&env).unwrap();
-    btleplug::platform::init(&env).unwrap();
+    // panicking here would abort the app, the error is reported by the first blec call instead
+    if let Err(e) = init_jni(&vm) {
+        let _ = LOAD_ERROR.set(e.to_string());
+    }
     let _ = JAVAVM.set(vm);
     jni::JNIVersion::V6.into()
 }
@@ -0,0 +1,512 @@
+//! Writes the operations of blec as HCI traffic into a btsnoop or pcap file that can be opened in Wireshark.
+//!
+//! The platform APIs don't expose the real HCI packets, so they are synthesized:
+//! connects and disconnects become LE Connection Complete and Disconnection Complete events,
+//! reads, writes, subscriptions and notifications become ATT PDUs.
+//! Attribute handles are assigned by blec, a characteristic discovery is added after every connect
+//! so Wireshark can map them to the characteristic uuids.
+
+use crate::{BleAddress, BleAddressType, BleError, DisconnectReason};
+use btleplug::api::{CharPropFlags, Characteristic, WriteType};
+#[cfg(not(feature = "tracing"))]
+use log::error;
+use std::fs::File;
+use std::io::{self, Write};
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+#[cfg(feature = "tracing")]
+use tracing::error;
+use uuid::Uuid;
+
+/// File format of the trace
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TraceFormat {
+    /// btsnoop with HCI UART (H4) datalink, as written by Android's HCI snoop log
+    Btsnoop,
+    /// pcap with `LINKTYPE_BLUETOOTH_HCI_H4_WITH_PHDR`
+    Pcap,
+}
+
+const BTSNOOP_DATALINK_H4: u32 = 1002;
+/// microseconds between 0 AD and the unix epoch as used by btsnoop
+const BTSNOOP_EPOCH_OFFSET: u64 = 0x00dc_ddb3_0f2f_8000;
+const LINKTYPE_BLUETOOTH_HCI_H4_WITH_PHDR: u32 = 201;
+
+const H4_ACL: u8 = 0x02;
+const H4_EVENT: u8 = 0x04;
+/// connection handle used for all connections, blec only has one at a time
+const CONN_HANDLE: u16 = 0x0040;
+/// packet boundary flag of the first (and only) fragment of an L2CAP frame
+const ACL_START: u16 = 0x2000;
+const ATT_CID: u16 = 0x0004;
+
+const EVT_DISCONNECTION_COMPLETE: u8 = 0x05;
+const EVT_LE_META: u8 = 0x3e;
+const LE_CONNECTION_COMPLETE: u8 = 0x01;
+
+const ATT_ERROR_RSP: u8 = 0x01;
+const ATT_READ_BY_TYPE_REQ: u8 = 0x08;
+const ATT_READ_BY_TYPE_RSP: u8 = 0x09;
+const ATT_READ_REQ: u8 = 0x0a;
+const ATT_READ_RSP: u8 = 0x0b;
+const ATT_WRITE_REQ: u8 = 0x12;
+const ATT_WRITE_RSP: u8 = 0x13;
+const ATT_HANDLE_VALUE_NTF: u8 = 0x1b;
+const ATT_WRITE_CMD: u8 = 0x52;
+const ATT_ATTRIBUTE_NOT_FOUND: u8 = 0x0a;
+const ATT_UNLIKELY_ERROR: u8 = 0x0e;
+const GATT_CHARACTERISTIC: u16 = 0x2803;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Direction {
+    /// host to controller
+    Sent,
+    Received,
+}
+
+struct Tracer {
+    file: File,
+    format: TraceFormat,
+    /// characteristics of the connection, their position defines the attribute handles
+    characs: Vec<(Uuid, CharPropFlags)>,
+}
+
+static TRACER: Mutex<Option<Tracer>> = Mutex::new(None);
+
+/// Starts writing a trace to the file, a running trace is stopped.
+/// Characteristics of a connection established before get handles on first use, without uuids shown in Wireshark.
+pub fn start_trace(path: impl AsRef<Path>, format: TraceFormat) -> Result<(), BleError> {
+    let mut file = File::create(path).map_err(BleError::Trace)?;
+    file.write_all(&file_header(format))
+        .map_err(BleError::Trace)?;
+    *TRACER.lock().unwrap_or_else(|e| e.into_inner()) = Some(Tracer {
+        file,
+        format,
+        characs: vec![],
+    });
+    Ok(())
+}
+
+/// Stops tracing and closes the file
+pub fn stop_trace() {
+    TRACER.lock().unwrap_or_else(|e| e.into_inner()).take();
+}
+
+pub fn is_tracing() -> bool {
+    TRACER.lock().unwrap_or_else(|e| e.into_inner()).is_some()
+}
+
+fn file_header(format: TraceFormat) -> Vec<u8> {
+    let mut header = vec![];
+    match format {
+        TraceFormat::Btsnoop => {
+            header.extend(b"btsnoop\0");
+            header.extend(1u32.to_be_bytes());
+            header.extend(BTSNOOP_DATALINK_H4.to_be_bytes());
+        }
+        TraceFormat::Pcap => {
+            header.extend(0xa1b2_c3d4u32.to_le_bytes());
+            header.extend(2u16.to_le_bytes());
+            header.extend(4u16.to_le_bytes());
+            // timezone and timestamp accuracy
+            header.extend([0; 8]);
+            // snapshot length
+            header.extend(65535u32.to_le_bytes());
+            header.extend(LINKTYPE_BLUETOOTH_HCI_H4_WITH_PHDR.to_le_bytes());
+        }
+    }
+    header
+}
+
+/// Runs `f` with the tracer if tracing, the trace is stopped if writing fails
+fn with_tracer(f: impl FnOnce(&mut Tracer) -> io::Result<()>) {
+    let mut tracer = TRACER.lock().unwrap_or_else(|e| e.into_inner());
+    if let Some(t) = tracer.as_mut() {
+        if let Err(e) = f(t) {
+            error!("writing the trace failed, stopping it: {e}");
+            *tracer = None;
+        }
+    }
+}
+
+impl Tracer {
+    fn write_packet(&mut self, direction: Direction, packet: &[u8]) -> io::Result<()> {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default();
+        let received = direction == Direction::Received;
+        let mut record = vec![];
+        match self.format {
+            TraceFormat::Btsnoop => {
+                let len = packet.len() as u32;
+                let is_event = packet[0] == H4_EVENT;
+                let flags = received as u32 | (is_event as u32) << 1;
+                let timestamp = now.as_micros() as u64 + BTSNOOP_EPOCH_OFFSET;
+                record.extend(len.to_be_bytes());
+                record.extend(len.to_be_bytes());
+                record.extend(flags.to_be_bytes());
+                // cumulative drops
+                record.extend(0u32.to_be_bytes());
+                record.extend(timestamp.to_be_bytes());
+            }
+            TraceFormat::Pcap => {
+                let len = packet.len() as u32 + 4;
+                record.extend((now.as_secs() as u32).to_le_bytes());
+                record.extend(now.subsec_micros().to_le_bytes());
+                record.extend(len.to_le_bytes());
+                record.extend(len.to_le_bytes());
+                // pseudo header with the direction
+                record.extend((received as u32).to_be_bytes());
+            }
+        }
+        record.extend(packet);
+        self.file.write_all(&record)
+    }
+
+    fn write_att(&mut self, direction: Direction, pdu: &[u8]) -> io::Result<()> {
+        let mut packet = vec![H4_ACL];
+        packet.extend((CONN_HANDLE | ACL_START).to_le_bytes());
+        packet.extend((pdu.len() as u16 + 4).to_le_bytes());
+        packet.extend((pdu.len() as u16).to_le_bytes());
+        packet.extend(ATT_CID.to_le_bytes());
+        packet.extend(pdu);
+        self.write_packet(direction, &packet)
+    }
+
+    fn write_event(&mut self, code: u8, params: &[u8]) -> io::Result<()> {
+        let mut packet = vec![H4_EVENT, code, params.len() as u8];
+        packet.extend(params);
+        self.write_packet(Direction::Received, &packet)
+    }
+
+    /// Index of the characteristic, unknown characteristics are added
+    fn index(&mut self, uuid: Uuid) -> u16 {
+        let index = match self.characs.iter().position(|(u, _)| *u == uuid) {
+            Some(index) => index,
+            None => {
+                self.characs.push((uuid, CharPropFlags::empty()));
+                self.characs.len() - 1
+            }
+        };
+        index as u16
+    }
+
+    /// Every characteristic uses three handles: declaration, value and client configuration
+    fn value_handle(&mut self, uuid: Uuid) -> u16 {
+        self.index(uuid) * 3 + 2
+    }
+
+    fn config_handle(&mut self, uuid: Uuid) -> u16 {
+        self.index(uuid) * 3 + 3
+    }
+}
+
+/// ATT pdu with the opcode and handle followed by the data
+fn att_pdu(opcode: u8, handle: u16, data: &[u8]) -> Vec<u8> {
+    let mut pdu = vec![opcode];
+    pdu.extend(handle.to_le_bytes());
+    pdu.extend(data);
+    pdu
+}
+
+pub(crate) fn connected(
+    address: BleAddress,
+    address_type: Option<BleAddressType>,
+    characs: &[Characteristic],
+) {
+    with_tracer(|t| {
+        t.characs = characs.iter().map(|c| (c.uuid, c.properties)).collect();
+        let mut params = vec![LE_CONNECTION_COMPLETE, 0];
+        params.extend(CONN_HANDLE.to_le_bytes());
+        // central role
+        params.push(0);
+        params.push(matches!(address_type, Some(BleAddressType::Random)) as u8);
+        params.extend(address.address.iter().rev());
+        // connection interval, latency, supervision timeout and clock accuracy are not known
+        params.extend(0x0018u16.to_le_bytes());
+        params.extend(0u16.to_le_bytes());
+        params.extend(0x0048u16.to_le_bytes());
+        params.push(0);
+        t.write_event(EVT_LE_META, &params)?;
+        // characteristic discovery so Wireshark knows the uuids of the handles
+        let mut request = att_pdu(ATT_READ_BY_TYPE_REQ, 0x0001, &0xffffu16.to_le_bytes());
+        request.extend(GATT_CHARACTERISTIC.to_le_bytes());
+        t.write_att(Direction::Sent, &request)?;
+        if t.characs.is_empty() {
+            let response = error_rsp(ATT_READ_BY_TYPE_REQ, 0x0001, ATT_ATTRIBUTE_NOT_FOUND);
+            return t.write_att(Direction::Received, &response);
+        }
+        // each entry: declaration handle, properties, value handle and 128 bit uuid
+        let mut response = vec![ATT_READ_BY_TYPE_RSP, 21];
+        for (i, (uuid, properties)) in t.characs.iter().enumerate() {
+            let declaration = i as u16 * 3 + 1;
+            response.extend(declaration.to_le_bytes());
+            response.push(properties.bits());
+            response.extend((declaration + 1).to_le_bytes());
+            response.extend(uuid.as_bytes().iter().rev());
+        }
+        t.write_att(Direction::Received, &response)
+    });
+}
+
+pub(crate) fn disconnected(reason: DisconnectReason) {
+    let reason = match reason {
+        // connection terminated by local host
+        DisconnectReason::UserInitiated | DisconnectReason::AdapterOff => 0x16,
+        // remote user terminated connection
+        DisconnectReason::Remote => 0x13,
+        // connection timeout
+        DisconnectReason::LinkLoss => 0x08,
+    };
+    with_tracer(|t| {
+        let mut params = vec![0];
+        params.extend(CONN_HANDLE.to_le_bytes());
+        params.push(reason);
+        t.write_event(EVT_DISCONNECTION_COMPLETE, &params)
+    });
+}
+
+/// Failed writes are followed by an error response, also for writes without response
+pub(crate) fn write(
+    charac: Uuid,
+    data: &[u8],
+    write_type: WriteType,
+    result: &Result<(), BleError>,
+) {
+    let opcode = match write_type {
+        WriteType::WithoutResponse => ATT_WRITE_CMD,
+        WriteType::WithResponse => ATT_WRITE_REQ,
+    };
+    with_tracer(|t| {
+        let handle = t.value_handle(charac);
+        t.write_att(Direction::Sent, &att_pdu(opcode, handle, data))?;
+        match (result, write_type) {
+            (Err(_), _) => {
+                let response = error_rsp(opcode, handle, ATT_UNLIKELY_ERROR);
+                t.write_att(Direction::Received, &response)
+            }
+            (Ok(()), WriteType::WithResponse) => t.write_att(Direction::Received, &[ATT_WRITE_RSP]),
+            (Ok(()), WriteType::WithoutResponse) => Ok(()),
+        }
+    });
+}
+
+pub(crate) fn read(charac: Uuid, result: &Result<Vec<u8>, BleError>) {
+    with_tracer(|t| {
+        let handle = t.value_handle(charac);
+        t.write_att(Direction::Sent, &att_pdu(ATT_READ_REQ, handle, &[]))?;
+        match result {
+            Ok(data) => {
+                let mut response = vec![ATT_READ_RSP];
+                response.extend(data);
+                t.write_att(Direction::Received, &response)
+            }
+            Err(_) => {
+                let response = error_rsp(ATT_READ_REQ, handle, ATT_UNLIKELY_ERROR);
+                t.write_att(Direction::Received, &response)
+            }
+        }
+    });
+}
+
+/// Error response with the opcode of the failed request, its handle and the error code
+fn error_rsp(opcode: u8, handle: u16, code: u8) -> Vec<u8> {
+    let mut response = vec![ATT_ERROR_RSP, opcode];
+    response.extend(handle.to_le_bytes());
+    response.push(code);
+    response
+}
+
+/// Enabling notifications or indications is a write to the client configuration descriptor
+pub(crate) fn subscribe(charac: &Characteristic) {
+    let value: u16 = match charac.properties.contains(CharPropFlags::NOTIFY) {
+        true => 0x0001,
+        false => 0x0002,
+    };
+    with_tracer(|t| {
+        let handle = t.config_handle(charac.uuid);
+        let request = att_pdu(ATT_WRITE_REQ, handle, &value.to_le_bytes());
+        t.write_att(Direction::Sent, &request)?;
+        t.write_att(Direction::Received, &[ATT_WRITE_RSP])
+    });
+}
+
+pub(crate) fn notification(charac: Uuid, data: &[u8]) {
+    with_tracer(|t| {
+        let handle = t.value_handle(charac);
+        t.write_att(
+            Direction::Received,
+            &att_pdu(ATT_HANDLE_VALUE_NTF, handle, data),
+        )
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeSet;
+
+    /// the tracer is global
+    static SERIAL: Mutex<()> = Mutex::new(());
+
+    const CHARAC: Uuid = Uuid::from_u128(0x0000_2a19_0000_1000_8000_0080_5f9b_34fb);
+
+    /// Traces the operations and returns the file content
+    fn trace(format: TraceFormat, session: impl FnOnce()) -> Vec<u8> {
+        let _serial = SERIAL.lock().unwrap_or_else(|e| e.into_inner());
+        let path =
+            std::env::temp_dir().join(format!("blec-snoop-{}-{format:?}", std::process::id()));
+        start_trace(&path, format).unwrap();
+        session();
+        stop_trace();
+        let data = std::fs::read(&path);
+        let _ = std::fs::remove_file(&path);
+        data.unwrap()
+    }
+
+    /// Splits the records after the file header into (received, packet)
+    fn packets(format: TraceFormat, data: &[u8]) -> Vec<(bool, Vec<u8>)> {
+        let u32_at = |pos: usize, be: bool| {
+            let bytes = data[pos..pos + 4].try_into().unwrap();
+            match be {
+                true => u32::from_be_bytes(bytes),
+                false => u32::from_le_bytes(bytes),
+            }
+        };
+        let mut packets = vec![];
+        match format {
+            TraceFormat::Btsnoop => {
+                let mut pos = 16;
+                while pos < data.len() {
+                    let len = u32_at(pos, true) as usize;
+                    assert_eq!(u32_at(pos + 4, true) as usize, len);
+                    let flags = u32_at(pos + 8, true);
+                    let packet = data[pos + 24..pos + 24 + len].to_vec();
+                    // bit 1 marks events
+                    assert_eq!(flags >> 1 == 1, packet[0] == H4_EVENT);
+                    packets.push((flags & 1 == 1, packet));
+                    pos += 24 + len;
+                }
+            }
+            TraceFormat::Pcap => {
+                let mut pos = 24;
+                while pos < data.len() {
+                    let len = u32_at(pos + 8, false) as usize;
+                    assert_eq!(u32_at(pos + 12, false) as usize, len);
+                    let direction = u32_at(pos + 16, true);
+                    packets.push((direction == 1, data[pos + 20..pos + 16 + len].to_vec()));
+                    pos += 16 + len;
+                }
+            }
+        }
+        packets
+    }
+
+    fn att(pdu: &[u8]) -> Vec<u8> {
+        let mut packet = vec![H4_ACL, 0x40, 0x20];
+        packet.extend((pdu.len() as u16 + 4).to_le_bytes());
+        packet.extend((pdu.len() as u16).to_le_bytes());
+        packet.extend([0x04, 0x00]);
+        packet.extend(pdu);
+        packet
+    }
+
+    fn session() {
+        let address = "C0:11:22:33:44:55".parse().unwrap();
+        let charac = Characteristic {
+            uuid: CHARAC,
+            service_uuid: Uuid::nil(),
+            properties: CharPropFlags::READ | CharPropFlags::WRITE_WITHOUT_RESPONSE,
+            descriptors: BTreeSet::new(),
+        };
+        connected(address, Some(BleAddressType::Random), &[charac]);
+        write(
+            CHARAC,
+            &[1, 2],
+            WriteType::WithoutResponse,
+            &Err(BleError::NoDeviceConnected),
+        );
+        disconnected(DisconnectReason::Remote);
+    }
+
+    fn check_session(format: TraceFormat, data: &[u8]) {
+        let packets = packets(format, data);
+        let expected_connect = [
+            H4_EVENT,
+            EVT_LE_META,
+            19,
+            LE_CONNECTION_COMPLETE,
+            0,
+            0x40,
+            0x00,
+            0,
+            1,
+            0x55,
+            0x44,
+            0x33,
+            0x22,
+            0x11,
+            0xC0,
+            0x18,
+            0x00,
+            0x00,
+            0x00,
+            0x48,
+            0x00,
+            0,
+        ];
+        assert_eq!(packets[0], (true, expected_connect.to_vec()));
+        let discovery = att(&[ATT_READ_BY_TYPE_REQ, 0x01, 0x00, 0xff, 0xff, 0x03, 0x28]);
+        assert_eq!(packets[1], (false, discovery));
+        let mut entry = vec![ATT_READ_BY_TYPE_RSP, 21, 0x01, 0x00, 0x06, 0x02, 0x00];
+        entry.extend(CHARAC.as_bytes().iter().rev());
+        assert_eq!(packets[2], (true, att(&entry)));
+        assert_eq!(packets[3], (false, att(&[ATT_WRITE_CMD, 0x02, 0x00, 1, 2])));
+        let error = att(&[ATT_ERROR_RSP, ATT_WRITE_CMD, 0x02, 0x00, ATT_UNLIKELY_ERROR]);
+        assert_eq!(packets[4], (true, error));
+        let disconnect = vec![H4_EVENT, EVT_DISCONNECTION_COMPLETE, 4, 0, 0x40, 0x00, 0x13];
+        assert_eq!(packets[5], (true, disconnect));
+        assert_eq!(packets.len(), 6);
+    }
+
+    #[test]
+    fn writes_btsnoop() {
+        let data = trace(TraceFormat::Btsnoop, session);
+        assert_eq!(
+            data[..16],
+            [b'b', b't', b's', b'n', b'o', b'o', b'p', 0, 0, 0, 0, 1, 0, 0, 0x03, 0xea]
+        );
+        check_session(TraceFormat::Btsnoop, &data);
+    }
+
+    #[test]
+    fn writes_pcap() {
+        let data = trace(TraceFormat::Pcap, session);
+        assert_eq!(
+            data[..24],
+            [
+                0xd4, 0xc3, 0xb2, 0xa1, 2, 0, 4, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0xff, 0xff, 0, 0, 201,
+                0, 0, 0
+            ]
+        );
+        check_session(TraceFormat::Pcap, &data);
+    }
+
+    #[test]
+    fn discovery_without_characteristics_fails() {
+        let data = trace(TraceFormat::Btsnoop, || {
+            connected(BleAddress::default(), None, &[]);
+        });
+        let packets = packets(TraceFormat::Btsnoop, &data);
+        let error = att(&[
+            ATT_ERROR_RSP,
+            ATT_READ_BY_TYPE_REQ,
+            0x01,
+            0x00,
+            ATT_ATTRIBUTE_NOT_FOUND,
+        ]);
+        assert_eq!(packets[2], (true, error));
+        assert_eq!(packets.len(), 3);
+    }
+}
@@ -0,0 +1,147 @@
+//! Interface exported with [UniFFI](https://mozilla.github.io/uniffi-rs/) to generate Kotlin, Swift and Python bindings.
+//!
+//! blec is not built as a `cdylib` itself, add `blec::uniffi_reexport_scaffolding!();`
+//! to the `cdylib` crate the bindings are generated from.
+
+use crate::{ble, AdapterState, BleError, DisconnectReason};
+use std::collections::HashMap;
+use std::sync::Arc;
+use uuid::Uuid;
+
+#[derive(Debug, Clone, PartialEq, Eq, uniffi::Record)]
+pub struct BleAddress {
+    /// 6 bytes, most significant first
+    pub address: Vec<u8>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, uniffi::Record)]
+pub struct BleDevice {
+    /// platform-stable identifier, can be passed to [BleClient::connect]
+    pub id: String,
+    pub address: BleAddress,
+    pub name: String,
+    pub is_connected: bool,
+    pub rssi: Option<i16>,
+    pub services: Vec<String>,
+    pub manufacturer_data: HashMap<u16, Vec<u8>>,
+    pub service_data: HashMap<String, Vec<u8>>,
+}
+
+impl From<crate::BleAddress> for BleAddress {
+    fn from(address: crate::BleAddress) -> Self {
+        Self {
+            address: address.address.to_vec(),
+        }
+    }
+}
+
+impl From<crate::BleDevice> for BleDevice {
+    fn from(device: crate::BleDevice) -> Self {
+        Self {
+            id: device.id.to_string(),
+            address: device.address.into(),
+            name: device.name,
+            is_connected: device.is_connected,
+            rssi: device.rssi,
+            services: device.services.iter().map(Uuid::to_string).collect(),
+            manufacturer_data: device.manufacturer_data,
+            service_data: device
+                .service_data
+                .into_iter()
+                .map(|(uuid, data)| (uuid.to_string(), data))
+                .collect(),
+        }
+    }
+}
+
+#[uniffi::export(callback_interface)]
+pub trait NotificationListener: Send + Sync {
+    fn on_notification(&self, data: Vec<u8>);
+}
+
+#[uniffi::export(callback_interface)]
+pub trait DisconnectListener: Send + Sync {
+    fn on_disconnect(&self, device: BleDevice, reason: DisconnectReason);
+}
+
+/// Parses an address like `AA:BB:CC:DD:EE:FF`
+#[uniffi::export]
+pub fn parse_address(address: String) -> Result<BleAddress, BleError> {
+    Ok(address.parse::<crate::BleAddress>()?.into())
+}
+
+/// Entry point of the bindings, creating it initializes blec
+#[derive(Debug, uniffi::Object)]
+pub struct BleClient;
+
+#[uniffi::export]
+impl BleClient {
+    #[uniffi::constructor]
+    pub fn new() -> Result<Arc<Self>, BleError> {
+        crate::init()?;
+        Ok(Arc::new(Self))
+    }
+
+    /// Disconnects and stops blec, a new client can be created afterwards
+    pub fn deinit(&self) -> Result<(), BleError> {
+        crate::deinit()
+    }
+
+    pub fn adapter_state(&self) -> AdapterState {
+        ble::adapter_state()
+    }
+
+    /// Scans for [timeout_ms] milliseconds and returns the discovered devices
+    pub async fn discover(&self, timeout_ms: u64) -> Result<Vec<BleDevice>, BleError> {
+        let devices = ble::discover_async(timeout_ms).await?;
+        Ok(devices.into_iter().map(Into::into).collect())
+    }
+
+    /// Connects to the device with the given address or device id, unknown ids are scanned for
+    pub async fn connect(
+        &self,
+        target: String,
+        service: String,
+        characs: Vec<String>,
+        listener: Option<Box<dyn DisconnectListener>>,
+    ) -> Result<(), BleError> {
+        let target = ble::parse_target(target).await?;
+        let service = Uuid::parse_str(&service)?;
+        let characs = ble::parse_uuids(&characs)?;
+        let on_disconnect = listener.map(|listener| {
+            move |device: crate::BleDevice, reason| listener.on_disconnect(device.into(), reason)
+        });
+        ble::connect(target, service, characs, on_disconnect).await
+    }
+
+    pub async fn disconnect(&self) -> Result<(), BleError> {
+        ble::disconnect().await
+    }
+
+    pub async fn send_data(&self, charac: String, data: Vec<u8>) -> Result<(), BleError> {
+        ble::send_data(Uuid::parse_str(&charac)?, data).await
+    }
+
+    pub async fn recv_data(&self, charac: String) -> Result<Vec<u8>, BleError> {
+        ble::recv_data(Uuid::parse_str(&charac)?).await
+    }
+
+    pub async fn subscribe(
+        &self,
+        charac: String,
+        listener: Box<dyn NotificationListener>,
+    ) -> Result<(), BleError> {
+        ble::subscribe(Uuid::parse_str(&charac)?, move |data: &[u8]| {
+            listener.on_notification(data.to_vec())
+        })
+        .await
+    }
+
+    pub async fn is_connected(&self) -> Result<bool, BleError> {
+        ble::is_connected().await
+    }
+
+    pub async fn connected_device(&self) -> Result<BleDevice, BleError> {
+        Ok(ble::connected_device().await?.into())
+    }
+}
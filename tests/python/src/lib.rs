@@ -0,0 +1,7 @@
+// extension module exposing the blec bindings as `blec`
+use pyo3::prelude::*;
+
+#[pymodule]
+pub fn blec(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    ::blec::python::register(m)
+}
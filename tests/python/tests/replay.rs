@@ -0,0 +1,20 @@
+use blec_python_tests::blec;
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+use std::ffi::CString;
+
+#[test]
+fn python_bindings_replay_session() {
+    pyo3::append_to_inittab!(blec);
+    let fixture = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/session.json");
+    let script = CString::new(include_str!("test_replay.py")).unwrap();
+    Python::with_gil(|py| {
+        let globals = PyDict::new(py);
+        globals.set_item("FIXTURE", fixture)?;
+        py.run(&script, Some(&globals), None)
+    })
+    .unwrap_or_else(|e| {
+        Python::with_gil(|py| e.print(py));
+        panic!("python test failed");
+    });
+}
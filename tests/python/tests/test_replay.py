@@ -0,0 +1,62 @@
+# Runs the python bindings against tests/session.json, `FIXTURE` is set by replay.rs
+import asyncio
+
+import blec
+
+SERVICE = "0000180f-0000-1000-8000-00805f9b34fb"
+BATTERY_LEVEL = "00002a19-0000-1000-8000-00805f9b34fb"
+MISSING = "00002a1a-0000-1000-8000-00805f9b34fb"
+
+
+async def session():
+    loop = asyncio.get_running_loop()
+    notifications = asyncio.Queue()
+    disconnected = loop.create_future()
+
+    devices = await blec.discover(5000)
+    assert [(d.name, d.address, d.rssi) for d in devices] == [("Battery", "AA:BB:CC:DD:EE:FF", -60)]
+    assert devices[0].services == [SERVICE]
+
+    def on_disconnect(device, reason):
+        loop.call_soon_threadsafe(disconnected.set_result, (device.name, reason))
+
+    await blec.connect(devices[0].address, SERVICE, [BATTERY_LEVEL], on_disconnect)
+    assert await blec.is_connected()
+    assert (await blec.connected_device()).is_connected
+
+    await blec.subscribe(BATTERY_LEVEL, lambda data: loop.call_soon_threadsafe(notifications.put_nowait, data))
+    assert await asyncio.wait_for(notifications.get(), 5) == b"\x57"
+
+    await blec.send_data(BATTERY_LEVEL, [1, 2])
+    assert await blec.recv_data(BATTERY_LEVEL) == b"\x56"
+    try:
+        await blec.recv_data(MISSING)
+        raise AssertionError("recv_data did not raise")
+    except blec.BleException as e:
+        assert MISSING in str(e), e
+
+    name, reason = await asyncio.wait_for(disconnected, 5)
+    assert (name, reason) == ("Battery", blec.DisconnectReason.Remote)
+    assert not await blec.is_connected()
+
+
+async def diverged():
+    await blec.discover(5000)
+    try:
+        await blec.connect("11:22:33:44:55:66", SERVICE, [BATTERY_LEVEL])
+        raise AssertionError("connect did not raise")
+    except blec.BleException as e:
+        assert "diverged" in str(e), e
+
+
+blec.start_replay(FIXTURE, timing=False)
+asyncio.run(session())
+blec.stop_replay()
+
+blec.start_replay(FIXTURE)
+asyncio.run(diverged())
+try:
+    blec.stop_replay()
+    raise AssertionError("stop_replay did not raise")
+except blec.BleException:
+    pass
@@ -0,0 +1 @@
+blec::uniffi_reexport_scaffolding!();
@@ -0,0 +1,24 @@
+# Smoke test of the generated Python bindings, runs without a bluetooth adapter
+
+import blec
+
+address = blec.parse_address("aa-bb-cc-dd-ee-0f")
+assert address == blec.BleAddress(address=b"\xaa\xbb\xcc\xdd\xee\x0f"), address
+
+try:
+    blec.parse_address("not an address")
+    raise AssertionError("invalid address was accepted")
+except blec.BleError.InvalidAddress as e:
+    assert "invalid length" in str(e), e
+
+assert blec.DisconnectReason.LINK_LOSS != blec.DisconnectReason.REMOTE
+assert blec.AdapterState.POWERED_ON
+
+# without an adapter creating the client fails with a BleError instead of crashing
+try:
+    client = blec.BleClient()
+except blec.BleError as e:
+    print(f"no adapter available: {e!r}")
+else:
+    assert isinstance(client.adapter_state(), blec.AdapterState)
+    client.deinit()